@@ -1,15 +1,26 @@
 use chrono::Utc;
+use futures_util::{Stream, StreamExt};
 use serde_json::Value;
+use tracing::warn;
 use crate::models::{LogSource, SecurityAlert};
 use crate::llmprovider::LLMProvider;
+use crate::metrics::SCHEMA_VALIDATION_FAILURES;
+use crate::schema;
 
 pub struct Agent {
     provider: Box<dyn LLMProvider>,
+    model: String,
 }
 
 impl Agent {
-    pub fn new(provider: Box<dyn LLMProvider>) -> Self {
-        Self { provider }
+    pub fn new(provider: Box<dyn LLMProvider>, model: impl Into<String>) -> Self {
+        Self { provider, model: model.into() }
+    }
+
+    /// The backend name (e.g. `"OpenAI"`) and configured model, for labeling
+    /// per-provider metrics at the call site.
+    pub fn metric_labels(&self) -> (String, &str) {
+        (self.provider.name(), self.model.as_str())
     }
 
     pub async fn analyze(&self, line: &str, source: &LogSource) -> Option<SecurityAlert> {
@@ -19,18 +30,46 @@ impl Agent {
             return None;
         }
 
-        if let Ok(temp_alert) = serde_json::from_str::<Value>(&result_str) {
-            return Some(SecurityAlert {
-                timestamp: Utc::now().to_rfc3339(),
-                source_type: source.as_str().to_string(),
-                severity: temp_alert["severity"].as_str().unwrap_or("LOW").to_string(),
-                attack_type: temp_alert["attack_type"].as_str().unwrap_or("Unknown").to_string(),
-                description: temp_alert["description"].as_str().unwrap_or("").to_string(),
-                original_log: line.to_string(),
-            });
-        }
+        let verdict = match Self::validated_verdict(&result_str) {
+            Ok(v) => v,
+            Err(errors) => {
+                warn!(errors = %errors, "LLM verdict failed schema validation, re-prompting once");
+
+                let repair_line = format!(
+                    "{}\n\n[Your previous answer failed these constraints: {}. Respond again with a single corrected JSON object.]",
+                    line, errors
+                );
+                let retry_str = self.provider.analyze(&repair_line, source).await.ok()?;
+                if retry_str.contains("NULL") {
+                    return None;
+                }
 
-        None
+                match Self::validated_verdict(&retry_str) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        SCHEMA_VALIDATION_FAILURES.inc();
+                        return None;
+                    }
+                }
+            }
+        };
+
+        Some(SecurityAlert {
+            timestamp: Utc::now().to_rfc3339(),
+            source_type: source.as_str().to_string(),
+            severity: verdict["severity"].as_str().unwrap_or("LOW").to_string(),
+            attack_type: verdict["attack_type"].as_str().unwrap_or("Unknown").to_string(),
+            description: verdict["description"].as_str().unwrap_or("").to_string(),
+            original_log: line.to_string(),
+        })
+    }
+
+    /// Parses `raw` and validates it against the single-verdict JSON Schema,
+    /// returning the validation errors (joined into one string) on failure.
+    fn validated_verdict(raw: &str) -> Result<Value, String> {
+        let parsed = serde_json::from_str::<Value>(raw).map_err(|e| e.to_string())?;
+        schema::validate_verdict(&parsed)?;
+        Ok(parsed)
     }
 
     pub async fn analyze_batch(&self, lines: &[String], source: &LogSource) -> Vec<SecurityAlert> {
@@ -40,30 +79,114 @@ impl Agent {
             Err(_) => return alerts,
         };
 
-        if let Ok(Value::Array(results)) = serde_json::from_str::<Value>(&result_str) {
-            for item in results {
-                let status = item["status"].as_str().unwrap_or("");
-                if status == "NULL" {
-                    continue;
+        let results = match Self::validated_batch_results(&result_str) {
+            Some(results) => results,
+            None => {
+                warn!("Batch verdict failed schema validation, re-prompting once");
+
+                let repair_lines = match lines.split_first() {
+                    Some((first, rest)) => {
+                        let mut repaired = vec![format!(
+                            "{} [NOTE: the previous batch answer failed JSON Schema validation; return well-formed JSON objects this time, each with 'index' and either 'status': 'NULL' or 'severity'/'attack_type'/'description'.]",
+                            first
+                        )];
+                        repaired.extend(rest.iter().cloned());
+                        repaired
+                    }
+                    None => return alerts,
+                };
+
+                let retry_str = match self.provider.analyze_batch(&repair_lines, source).await {
+                    Ok(s) => s,
+                    Err(_) => return alerts,
+                };
+
+                match Self::validated_batch_results(&retry_str) {
+                    Some(results) => results,
+                    None => {
+                        SCHEMA_VALIDATION_FAILURES.inc();
+                        return alerts;
+                    }
                 }
+            }
+        };
+
+        for item in results {
+            let status = item["status"].as_str().unwrap_or("");
+            if status == "NULL" {
+                continue;
+            }
+
+            let index = item["index"].as_u64().unwrap_or(0) as usize;
+            if index >= lines.len() {
+                continue;
+            }
+
+            alerts.push(SecurityAlert {
+                timestamp: Utc::now().to_rfc3339(),
+                source_type: source.as_str().to_string(),
+                severity: item["severity"].as_str().unwrap_or("LOW").to_string(),
+                attack_type: item["attack_type"].as_str().unwrap_or("Unknown").to_string(),
+                description: item["description"].as_str().unwrap_or("").to_string(),
+                original_log: lines[index].clone(),
+            });
+        }
+
+        alerts
+    }
 
-                let index = item["index"].as_u64().unwrap_or(0) as usize;
-                if index >= lines.len() {
-                    continue;
+    /// Parses `raw` and validates it against the batch JSON Schema (which
+    /// accepts both a bare array and the canonical `{"results": [...]}`
+    /// envelope), returning the individual result objects on success.
+    fn validated_batch_results(raw: &str) -> Option<Vec<Value>> {
+        let parsed = serde_json::from_str::<Value>(raw).ok()?;
+        schema::validate_batch(&parsed).ok()?;
+
+        match parsed {
+            Value::Array(results) => Some(results),
+            Value::Object(mut obj) => match obj.remove("results") {
+                Some(Value::Array(results)) => Some(results),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Like `analyze_batch`, but yields each confirmed alert as soon as its
+    /// verdict streams in instead of waiting for the whole batch to finish
+    /// generating, so dispatch can fire on an early CRITICAL without
+    /// waiting on the rest of the window.
+    pub async fn analyze_batch_stream(
+        &self,
+        lines: &[String],
+        source: &LogSource,
+    ) -> impl Stream<Item = SecurityAlert> + Send {
+        let raw = self.provider.analyze_batch_stream(lines, source).await;
+        let owned_lines = lines.to_vec();
+        let source_type = source.as_str().to_string();
+
+        raw.filter_map(move |item| {
+            let owned_lines = owned_lines.clone();
+            let source_type = source_type.clone();
+            async move {
+                let item = item.ok()?;
+                if item["status"].as_str().unwrap_or("") == "NULL" {
+                    return None;
                 }
 
-                alerts.push(SecurityAlert {
+                let index = item["index"].as_u64()? as usize;
+                let line = owned_lines.get(index)?;
+
+                Some(SecurityAlert {
                     timestamp: Utc::now().to_rfc3339(),
-                    source_type: source.as_str().to_string(),
+                    source_type,
                     severity: item["severity"].as_str().unwrap_or("LOW").to_string(),
                     attack_type: item["attack_type"].as_str().unwrap_or("Unknown").to_string(),
                     description: item["description"].as_str().unwrap_or("").to_string(),
-                    original_log: lines[index].clone(),
-                });
+                    original_log: line.clone(),
+                })
             }
-        }
-
-        alerts
+        })
     }
 }
 
@@ -104,7 +227,7 @@ mod tests {
     async fn test_agent_analyze_threat() {
         let response_json = r#"{"severity": "HIGH", "attack_type": "SQLi", "description": "SQL Injection detected"}"#;
         let provider = Box::new(MockLLMProvider::new(response_json));
-        let agent = Agent::new(provider);
+        let agent = Agent::new(provider, "mock-model");
         let source = LogSource::Tomcat;
         
         let alert = agent.analyze("SELECT * FROM users", &source).await.unwrap();
@@ -117,7 +240,7 @@ mod tests {
     #[tokio::test]
     async fn test_agent_analyze_non_threat() {
         let provider = Box::new(MockLLMProvider::new("NULL"));
-        let agent = Agent::new(provider);
+        let agent = Agent::new(provider, "mock-model");
         let source = LogSource::Nginx;
         
         let alert = agent.analyze("GET /index.html", &source).await;
@@ -128,7 +251,7 @@ mod tests {
     #[tokio::test]
     async fn test_agent_analyze_malformed_json() {
         let provider = Box::new(MockLLMProvider::new("not a json"));
-        let agent = Agent::new(provider);
+        let agent = Agent::new(provider, "mock-model");
         let source = LogSource::Dotnet;
         
         let alert = agent.analyze("Something happened", &source).await;
@@ -143,7 +266,7 @@ mod tests {
             {"index": 2, "severity": "MEDIUM", "attack_type": "XSS", "description": "Threat 2"}
         ]"#;
         let provider = Box::new(MockLLMProvider::new(batch_response));
-        let agent = Agent::new(provider);
+        let agent = Agent::new(provider, "mock-model");
         let logs = vec![
             "SELECT * FROM users".to_string(),
             "GET /normal".to_string(),
@@ -158,4 +281,32 @@ mod tests {
         assert_eq!(alerts[1].attack_type, "XSS");
         assert_eq!(alerts[1].original_log, logs[2]);
     }
+
+    #[tokio::test]
+    async fn test_agent_analyze_batch_stream() {
+        let batch_response = r#"[
+            {"index": 0, "severity": "HIGH", "attack_type": "SQLi", "description": "Threat 1"},
+            {"index": 1, "status": "NULL"},
+            {"index": 2, "severity": "MEDIUM", "attack_type": "XSS", "description": "Threat 2"}
+        ]"#;
+        let provider = Box::new(MockLLMProvider::new(batch_response));
+        let agent = Agent::new(provider, "mock-model");
+        let logs = vec![
+            "SELECT * FROM users".to_string(),
+            "GET /normal".to_string(),
+            "<script>alert(1)</script>".to_string()
+        ];
+
+        let stream = agent.analyze_batch_stream(&logs, &LogSource::Generic).await;
+        tokio::pin!(stream);
+
+        let mut alerts = Vec::new();
+        while let Some(alert) = stream.next().await {
+            alerts.push(alert);
+        }
+
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].attack_type, "SQLi");
+        assert_eq!(alerts[1].attack_type, "XSS");
+    }
 }
\ No newline at end of file
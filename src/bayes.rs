@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+lazy_static! {
+    /// Matches an IPv4 literal so `tokenize` can keep it intact instead of
+    /// splitting it into four meaningless numeric tokens.
+    static ref IP_LITERAL: Regex = Regex::new(r"(?:\d{1,3}\.){3}\d{1,3}").unwrap();
+}
+
+/// Per-token (threat, benign) occurrence counts, persisted to disk as JSON.
+#[derive(Default, Clone, Serialize, Deserialize)]
+struct TokenCounts {
+    counts: HashMap<String, (u64, u64)>,
+}
+
+/// A self-training Bayesian classifier used to score log lines locally before
+/// they are escalated to the LLM. Implements a Robinson-smoothed naive Bayes
+/// combined via Fisher's method, in the style of classic spam filters
+/// (e.g. SpamBayes/DSPAM).
+pub struct BayesianClassifier {
+    counts: RwLock<TokenCounts>,
+    path: PathBuf,
+    /// Robinson smoothing strength ("s" in the formula).
+    strength: f64,
+    /// Number of most-interesting tokens to combine per line.
+    top_n: usize,
+}
+
+impl BayesianClassifier {
+    pub fn new(path: impl Into<PathBuf>, strength: f64, top_n: usize) -> Self {
+        let path = path.into();
+        let counts = Self::load(&path);
+        Self {
+            counts: RwLock::new(counts),
+            path,
+            strength,
+            top_n,
+        }
+    }
+
+    fn load(path: &Path) -> TokenCounts {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                warn!(error = %e, "Bayes token table corrupt, starting fresh");
+                TokenCounts::default()
+            }),
+            Err(_) => TokenCounts::default(),
+        }
+    }
+
+    /// Persist the token table atomically (write to a sibling temp file, then
+    /// rename). Runs the actual file I/O on a blocking thread via
+    /// `spawn_blocking`, since `train` calls this from an async task on every
+    /// escalated line and shouldn't stall the runtime on disk writes.
+    async fn persist(path: PathBuf, counts: TokenCounts) {
+        let result = tokio::task::spawn_blocking(move || {
+            let tmp_path = path.with_extension("tmp");
+            serde_json::to_vec(&counts)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| std::fs::write(&tmp_path, bytes).map_err(|e| e.to_string()))
+                .and_then(|_| std::fs::rename(&tmp_path, &path).map_err(|e| e.to_string()))
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!(error = %e, "Failed to persist bayes token table"),
+            Err(e) => error!(error = %e, "Bayes persist task panicked"),
+        }
+    }
+
+    /// Tokenize a log line: split on non-alphanumeric boundaries while keeping
+    /// a handful of security-relevant tokens intact (`..`, `0x1f`, IPs, `select`, ...).
+    pub fn tokenize(line: &str) -> Vec<String> {
+        let lower = line.to_lowercase();
+        let mut tokens = Vec::new();
+
+        // Pull out IPv4 literals first so they survive as a single token
+        // instead of being split into four meaningless numeric tokens by the
+        // generic splitter below.
+        let mut last_end = 0;
+        for m in IP_LITERAL.find_iter(&lower) {
+            Self::tokenize_generic(&lower[last_end..m.start()], &mut tokens);
+            tokens.push(m.as_str().to_string());
+            last_end = m.end();
+        }
+        Self::tokenize_generic(&lower[last_end..], &mut tokens);
+
+        tokens.retain(|t| !t.is_empty());
+        tokens
+    }
+
+    /// Splits a segment (already known to contain no IPv4 literal) on
+    /// non-alphanumeric boundaries, keeping path-traversal markers intact.
+    fn tokenize_generic(segment: &str, tokens: &mut Vec<String>) {
+        let mut current = String::new();
+
+        let mut chars = segment.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_alphanumeric() {
+                current.push(c);
+            } else {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                // Keep the path-traversal marker `..` as a standalone token.
+                if c == '.' && chars.peek() == Some(&'.') {
+                    tokens.push("..".to_string());
+                }
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+    }
+
+    /// `p(w) = t / (t + b)` for a single token, without smoothing.
+    fn raw_probability(t: u64, b: u64) -> f64 {
+        if t + b == 0 {
+            0.5
+        } else {
+            t as f64 / (t + b) as f64
+        }
+    }
+
+    /// Robinson's smoothing: `f(w) = (s*0.5 + n*p(w)) / (s + n)`.
+    fn smoothed_probability(&self, t: u64, b: u64) -> f64 {
+        let n = (t + b) as f64;
+        let p = Self::raw_probability(t, b);
+        (self.strength * 0.5 + n * p) / (self.strength + n)
+    }
+
+    /// Regularized lower incomplete gamma CDF for a chi-square distribution with
+    /// `2n` degrees of freedom, which always has the closed form
+    /// `P(x; 2n) = 1 - e^(-x/2) * sum_{i=0}^{n-1} (x/2)^i / i!`.
+    fn chi_square_cdf(x: f64, n: usize) -> f64 {
+        if x <= 0.0 {
+            return 0.0;
+        }
+        let half_x = x / 2.0;
+        let mut term = 1.0_f64;
+        let mut sum = 1.0_f64;
+        for i in 1..n {
+            term *= half_x / i as f64;
+            sum += term;
+        }
+        (1.0 - (-half_x).exp() * sum).clamp(0.0, 1.0)
+    }
+
+    /// Score a log line in `[0, 1]`: combine the `top_n` most "interesting" tokens
+    /// (largest `|f(w) - 0.5|`) via Fisher's method.
+    pub fn score(&self, line: &str) -> f64 {
+        let tokens = Self::tokenize(line);
+        if tokens.is_empty() {
+            return 0.5;
+        }
+
+        let counts = self.counts.read().unwrap();
+        let mut probs: Vec<f64> = tokens
+            .iter()
+            .map(|tok| {
+                let (t, b) = counts.counts.get(tok).copied().unwrap_or((0, 0));
+                self.smoothed_probability(t, b)
+            })
+            .collect();
+        drop(counts);
+
+        probs.sort_by(|a, b| {
+            let da = (a - 0.5).abs();
+            let db = (b - 0.5).abs();
+            db.partial_cmp(&da).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        probs.truncate(self.top_n.max(1));
+
+        // Clamp away from the 0/1 extremes so ln() stays finite.
+        let clamped: Vec<f64> = probs.iter().map(|p| p.clamp(1e-9, 1.0 - 1e-9)).collect();
+        let n = clamped.len();
+
+        let h: f64 = -2.0 * clamped.iter().map(|p| p.ln()).sum::<f64>();
+        let s: f64 = -2.0 * clamped.iter().map(|p| (1.0 - p).ln()).sum::<f64>();
+
+        // Fisher's method uses the upper-tail (inverse chi-square) probability
+        // of each statistic, so threat-leaning tokens push the score toward 1.
+        let chi2_h = 1.0 - Self::chi_square_cdf(h, n);
+        let chi2_s = 1.0 - Self::chi_square_cdf(s, n);
+
+        ((1.0 + chi2_h - chi2_s) / 2.0).clamp(0.0, 1.0)
+    }
+
+    /// Feed back a confirmed outcome: bump the threat or benign count for every
+    /// token in the line and persist the updated table.
+    pub async fn train(&self, line: &str, is_threat: bool) {
+        let tokens = Self::tokenize(line);
+        let snapshot = {
+            let mut counts = self.counts.write().unwrap();
+            for tok in tokens {
+                let entry = counts.counts.entry(tok).or_insert((0, 0));
+                if is_threat {
+                    entry.0 += 1;
+                } else {
+                    entry.1 += 1;
+                }
+            }
+            counts.clone()
+        };
+        Self::persist(self.path.clone(), snapshot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classifier() -> BayesianClassifier {
+        BayesianClassifier::new(std::env::temp_dir().join(format!("bayes-test-{}.json", std::process::id())), 1.0, 5)
+    }
+
+    #[test]
+    fn test_tokenize_keeps_security_markers() {
+        let tokens = BayesianClassifier::tokenize(
+            "192.168.1.100 - GET ../../etc/passwd?id=1' OR '1'='1 SELECT * FROM users",
+        );
+        assert!(tokens.contains(&"..".to_string()));
+        assert!(tokens.contains(&"passwd".to_string()));
+        assert!(tokens.contains(&"select".to_string()));
+        assert!(tokens.contains(&"192.168.1.100".to_string()));
+        assert!(!tokens.contains(&"192".to_string()));
+    }
+
+    #[test]
+    fn test_untrained_line_scores_neutral() {
+        let c = classifier();
+        let score = c.score("a perfectly normal log line");
+        assert!((score - 0.5).abs() < 0.2);
+    }
+
+    #[tokio::test]
+    async fn test_training_shifts_score_toward_threat() {
+        let c = classifier();
+        for _ in 0..20 {
+            c.train("select * from users where id=1 or 1=1", true).await;
+        }
+        for _ in 0..20 {
+            c.train("user logged in successfully", false).await;
+        }
+
+        let threat_score = c.score("select * from accounts where 1=1");
+        let benign_score = c.score("user logged in successfully");
+
+        assert!(threat_score > benign_score);
+        let _ = std::fs::remove_file(c.path.clone());
+        let _ = std::fs::remove_file(c.path.with_extension("tmp"));
+    }
+}
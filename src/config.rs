@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use crate::models::LogSource;
 use secrecy::SecretString;
 
@@ -32,6 +33,25 @@ pub struct FileConfig {
     pub enabled: bool,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExecConfig {
+    pub enabled: bool,
+    pub command: String,
+    pub args: Vec<String>,
+    pub timeout_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RedisConfig {
+    pub enabled: bool,
+    pub url: String,
+    pub stream_key: String,
+    /// Approximate `MAXLEN` trim applied to the stream; ignored in pub/sub mode.
+    pub max_len: Option<usize>,
+    pub use_pubsub: bool,
+    pub channel: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ThreatSignature {
     pub id: String,
@@ -81,18 +101,141 @@ pub struct MetricsConfig {
     pub port: u16,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct DigestConfig {
+    pub enabled: bool,
+    pub flush_interval_secs: u64,
+    pub max_samples: usize,
+    /// When false, only rate-limiter-suppressed alerts are accumulated. When
+    /// true, every alert is also folded into the digest for extra visibility.
+    pub record_all: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ResponseConfig {
+    pub enabled: bool,
+    /// Number of HIGH/CRITICAL alerts from the same IP within `window_secs` that trigger a ban.
+    pub threshold: usize,
+    pub window_secs: u64,
+    pub ban_duration_secs: u64,
+    /// Per-`LogSource` overrides (keyed by its lowercase name, e.g. "nginx")
+    /// of the default first-IPv4/IPv6-match regex used to pull the source IP
+    /// out of `original_log`. Sources without an entry use the default.
+    #[serde(default)]
+    pub ip_regex: HashMap<String, String>,
+    /// "ipset" or "shell".
+    pub action: String,
+    pub ipset_name: String,
+    pub shell_ban_command: String,
+    pub shell_unban_command: String,
+    pub state_path: String,
+    pub expiry_sweep_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FirewallConfig {
+    pub enabled: bool,
+    pub ban_duration_secs: u64,
+    /// Overrides the default first-IPv4/IPv6-match regex used to pull the source IP out of `original_log`.
+    pub ip_regex: Option<String>,
+    /// "ipset" or "shell".
+    pub action: String,
+    pub ipset_name: String,
+    pub shell_ban_command: String,
+    pub shell_unban_command: String,
+    /// When true, log the ban that would be applied without touching the firewall.
+    pub dry_run: bool,
+    pub expiry_sweep_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct IngestConfig {
+    /// "file" (default, tails `log_path`), "tcp", or "zmq".
+    pub kind: String,
+    pub tcp_bind_addr: String,
+    pub zmq_endpoint: String,
+    pub zmq_topic: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub bind_addr: String,
+    pub recent_alerts_capacity: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SpoolConfig {
+    pub dir: String,
+    /// Max age of a spool entry, counted from when it was spooled, before it
+    /// moves to dead-letter while any sink is unacked. One limit for all sinks.
+    pub max_age_secs: u64,
+    /// Backpressure: reject new entries once the spool holds this many.
+    pub quota: usize,
+    pub sweep_interval_secs: u64,
+    /// Per-sink attempt ceiling; once every unacked sink for an entry hits
+    /// this, the entry moves to dead-letter regardless of its age.
+    pub max_attempts: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BayesConfig {
+    pub enabled: bool,
+    /// Where the threat/benign token-frequency table is persisted.
+    pub data_path: String,
+    /// Robinson smoothing strength ("s" in the formula).
+    pub smoothing_strength: f64,
+    /// Number of most "interesting" tokens combined via Fisher's method.
+    pub top_n_tokens: usize,
+    /// Lines scoring below this are dropped without ever reaching the LLM.
+    pub low_threshold: f64,
+    /// Lines scoring above this are alerted on directly, skipping the LLM.
+    pub high_threshold: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MockConfig {
+    /// In "mock" mode, the fixture `MockProvider` serves canned verdicts
+    /// from. In "record"/"replay" mode, where `RecordReplayProvider`
+    /// persists/reads its captured `(prompt, raw_response)` pairs.
+    pub fixture_path: String,
+    /// "off" (default, live providers pass through untouched), "record"
+    /// (wrap the configured provider, capturing every call to `fixture_path`),
+    /// or "replay" (serve captured responses, never touching the network).
+    pub mode: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HttpConfig {
+    /// Applied to every request made by the shared `reqwest::Client` (BFF, Slack, email sinks).
+    pub timeout_secs: u64,
+    pub pool_max_idle_per_host: usize,
+    pub proxy: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Settings {
     pub server: ServerConfig,
+    pub http: HttpConfig,
     pub bff: BffConfig,
     pub email: EmailConfig,
     pub rats: RateLimitConfig, // Using 'rats' as convenient short name or 'ratelimit'
     pub logger: FileConfig,
+    pub redis: RedisConfig,
+    pub exec: ExecConfig,
     pub log_path: String,
     pub source: LogSource,
     pub filter: LogFilterConfig,
     pub analysis: AnalysisConfig,
     pub metrics: MetricsConfig,
+    pub bayes: BayesConfig,
+    pub spool: SpoolConfig,
+    pub response: ResponseConfig,
+    pub digest: DigestConfig,
+    pub firewall: FirewallConfig,
+    pub control: ControlConfig,
+    pub ingest: IngestConfig,
+    pub mock: MockConfig,
 }
 
 impl Settings {
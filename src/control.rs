@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, error, info, warn};
+
+use crate::models::SecurityAlert;
+use crate::response::ActiveResponse;
+use crate::spool::AlertSpool;
+
+/// Shared state the control socket reads from and mutates, populated by the
+/// same pipeline that feeds the dispatcher and metrics.
+pub struct ControlState {
+    paused: AtomicBool,
+    recent_alerts: Mutex<VecDeque<SecurityAlert>>,
+    recent_alerts_capacity: usize,
+    analysis_tx: mpsc::Sender<String>,
+    analysis_capacity: usize,
+    active_response: Option<Arc<ActiveResponse>>,
+    spool: Arc<AlertSpool>,
+    reload_tx: mpsc::Sender<()>,
+}
+
+impl ControlState {
+    pub fn new(
+        recent_alerts_capacity: usize,
+        analysis_tx: mpsc::Sender<String>,
+        analysis_capacity: usize,
+        active_response: Option<Arc<ActiveResponse>>,
+        spool: Arc<AlertSpool>,
+        reload_tx: mpsc::Sender<()>,
+    ) -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            recent_alerts: Mutex::new(VecDeque::with_capacity(recent_alerts_capacity)),
+            recent_alerts_capacity,
+            analysis_tx,
+            analysis_capacity,
+            active_response,
+            spool,
+            reload_tx,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Record a dispatched alert for `alerts` introspection, evicting the
+    /// oldest once the ring buffer is full.
+    pub async fn record_alert(&self, alert: &SecurityAlert) {
+        let mut recent = self.recent_alerts.lock().await;
+        if recent.len() >= self.recent_alerts_capacity {
+            recent.pop_front();
+        }
+        recent.push_back(alert.clone());
+    }
+}
+
+#[derive(Serialize)]
+struct StatsResponse {
+    suspicious_logs_total: f64,
+    confirmed_threats_total: f64,
+    dispatch_failures_total: f64,
+    analysis_queue_depth: usize,
+    analysis_queue_capacity: usize,
+    paused: bool,
+    active_bans: usize,
+}
+
+async fn handle_command(state: &Arc<ControlState>, command: &str) -> serde_json::Value {
+    match command.trim().to_ascii_lowercase().as_str() {
+        "stats" => {
+            let depth = state.analysis_capacity.saturating_sub(state.analysis_tx.capacity());
+            let active_bans = state
+                .active_response
+                .as_ref()
+                .map(|r| r.active_bans().len())
+                .unwrap_or(0);
+            let stats = StatsResponse {
+                suspicious_logs_total: crate::metrics::SUSPICIOUS_LOGS.get(),
+                confirmed_threats_total: crate::metrics::sum_counter_vec(&crate::metrics::CONFIRMED_THREATS),
+                dispatch_failures_total: crate::metrics::sum_counter_vec(&crate::metrics::DISPATCH_FAILURES),
+                analysis_queue_depth: depth,
+                analysis_queue_capacity: state.analysis_capacity,
+                paused: state.is_paused(),
+                active_bans,
+            };
+            serde_json::to_value(stats).unwrap_or(json!({"error": "failed to serialize stats"}))
+        }
+        "alerts" => {
+            let recent = state.recent_alerts.lock().await;
+            json!(recent.iter().collect::<Vec<_>>())
+        }
+        "bans" => match &state.active_response {
+            Some(response) => {
+                let bans: Vec<serde_json::Value> = response
+                    .active_bans()
+                    .into_iter()
+                    .map(|(ip, remaining)| json!({"ip": ip.to_string(), "remaining_secs": remaining.as_secs()}))
+                    .collect();
+                json!(bans)
+            }
+            None => json!([]),
+        },
+        "spool" => {
+            let statuses: Vec<serde_json::Value> = state
+                .spool
+                .status_snapshot()
+                .into_iter()
+                .map(|s| {
+                    json!({
+                        "id": s.id,
+                        "attack_type": s.attack_type,
+                        "severity": s.severity,
+                        "created_at_ms": s.created_at_ms,
+                        "sinks": s.sinks.into_iter().map(|(name, state)| json!({
+                            "sink": name,
+                            "acked": state.acked,
+                            "attempts": state.attempts,
+                        })).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            json!(statuses)
+        }
+        "reload-signatures" => {
+            if state.reload_tx.send(()).await.is_err() {
+                json!({"error": "signature reload listener is not running"})
+            } else {
+                json!({"status": "reload requested"})
+            }
+        }
+        "pause" => {
+            state.paused.store(true, Ordering::Relaxed);
+            info!("Control socket: pipeline paused");
+            json!({"status": "paused"})
+        }
+        "resume" => {
+            state.paused.store(false, Ordering::Relaxed);
+            info!("Control socket: pipeline resumed");
+            json!({"status": "resumed"})
+        }
+        other => json!({"error": format!("unknown command '{}'", other)}),
+    }
+}
+
+/// Line-based TCP control server: one command per connection, one JSON
+/// response line back, so `nc`/`socat` or a small script can drive it.
+pub async fn run_control_server(state: Arc<ControlState>, bind_addr: String) {
+    let listener = match TcpListener::bind(&bind_addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!(error = %e, addr = %bind_addr, "Failed to bind control socket");
+            return;
+        }
+    };
+    info!(addr = %bind_addr, "Control socket listening");
+
+    loop {
+        let (socket, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "Failed to accept control connection");
+                continue;
+            }
+        };
+        let state = Arc::clone(&state);
+
+        tokio::spawn(async move {
+            let (read_half, mut write_half) = socket.into_split();
+            let mut lines = BufReader::new(read_half).lines();
+
+            match lines.next_line().await {
+                Ok(Some(line)) => {
+                    debug!(peer = %peer, command = %line, "Control socket command received");
+                    let response = handle_command(&state, &line).await;
+                    let mut payload = response.to_string();
+                    payload.push('\n');
+                    if let Err(e) = write_half.write_all(payload.as_bytes()).await {
+                        warn!(error = %e, peer = %peer, "Failed to write control socket response");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, peer = %peer, "Failed to read control socket command"),
+            }
+        });
+    }
+}
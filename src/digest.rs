@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use tracing::info;
+
+use crate::dispatcher::AlertSink;
+use crate::models::SecurityAlert;
+
+#[derive(Hash, Eq, PartialEq, Clone)]
+struct DigestKey {
+    source_type: String,
+    attack_type: String,
+    severity: String,
+}
+
+struct DigestEntry {
+    count: u64,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    samples: Vec<String>,
+}
+
+/// Accumulates alerts that would otherwise vanish into the void (suppressed
+/// by the rate limiter, or everything, if `record_all` is set) and produces
+/// one periodic digest instead of a per-event flood.
+pub struct AlertDigest {
+    entries: DashMap<DigestKey, DigestEntry>,
+    max_samples: usize,
+    pub record_all: bool,
+}
+
+impl AlertDigest {
+    pub fn new(max_samples: usize, record_all: bool) -> Self {
+        Self {
+            entries: DashMap::new(),
+            max_samples,
+            record_all,
+        }
+    }
+
+    pub fn record(&self, alert: &SecurityAlert) {
+        let key = DigestKey {
+            source_type: alert.source_type.clone(),
+            attack_type: alert.attack_type.clone(),
+            severity: alert.severity.clone(),
+        };
+        let now = Utc::now();
+
+        self.entries
+            .entry(key)
+            .and_modify(|entry| {
+                entry.count += 1;
+                entry.last_seen = now;
+                if entry.samples.len() < self.max_samples {
+                    entry.samples.push(alert.original_log.clone());
+                }
+            })
+            .or_insert_with(|| DigestEntry {
+                count: 1,
+                first_seen: now,
+                last_seen: now,
+                samples: vec![alert.original_log.clone()],
+            });
+    }
+
+    /// Drain the accumulator and render a human-readable summary, one line
+    /// per `(source_type, attack_type, severity)` bucket. Returns `None` if
+    /// nothing was recorded since the last flush.
+    pub fn flush(&self) -> Option<String> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let mut lines = Vec::new();
+        for entry in self.entries.iter() {
+            let key = entry.key();
+            let value = entry.value();
+            let samples = value.samples.join(" | ");
+            lines.push(format!(
+                "[{}/{} - {}] {} occurrence(s) between {} and {}. Samples: {}",
+                key.source_type,
+                key.attack_type,
+                key.severity,
+                value.count,
+                value.first_seen.to_rfc3339(),
+                value.last_seen.to_rfc3339(),
+                samples
+            ));
+        }
+        self.entries.clear();
+
+        Some(lines.join("\n"))
+    }
+
+    /// Periodically flush and push the resulting summary (if any) through the
+    /// configured sinks as a single digest alert.
+    pub async fn run_flush_loop(self: Arc<Self>, sinks: Arc<Vec<Box<dyn AlertSink>>>, interval_secs: u64) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            let Some(summary) = self.flush() else {
+                continue;
+            };
+
+            info!("Flushing alert digest covering the last interval");
+            let digest_alert = SecurityAlert {
+                timestamp: Utc::now().to_rfc3339(),
+                source_type: "LogSentinel".to_string(),
+                severity: "LOW".to_string(),
+                attack_type: "Digest Summary".to_string(),
+                description: "Aggregated alert digest for the last interval".to_string(),
+                original_log: summary,
+            };
+
+            for sink in sinks.iter() {
+                if let Err(e) = sink.send(&digest_alert).await {
+                    tracing::error!(error = %e, "Failed to deliver alert digest to a sink");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(source: &str, attack: &str, severity: &str, log: &str) -> SecurityAlert {
+        SecurityAlert {
+            timestamp: Utc::now().to_rfc3339(),
+            source_type: source.to_string(),
+            severity: severity.to_string(),
+            attack_type: attack.to_string(),
+            description: "test".to_string(),
+            original_log: log.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_flush_is_none_when_empty() {
+        let digest = AlertDigest::new(3, true);
+        assert!(digest.flush().is_none());
+    }
+
+    #[test]
+    fn test_groups_by_source_attack_severity() {
+        let digest = AlertDigest::new(3, true);
+        digest.record(&alert("Nginx", "SQLi", "HIGH", "a"));
+        digest.record(&alert("Nginx", "SQLi", "HIGH", "b"));
+        digest.record(&alert("Nginx", "XSS", "LOW", "c"));
+
+        let summary = digest.flush().unwrap();
+        assert!(summary.contains("Nginx/SQLi - HIGH] 2 occurrence"));
+        assert!(summary.contains("Nginx/XSS - LOW] 1 occurrence"));
+
+        assert!(digest.flush().is_none());
+    }
+
+    #[test]
+    fn test_sample_cap() {
+        let digest = AlertDigest::new(2, true);
+        for i in 0..5 {
+            digest.record(&alert("Tomcat", "Brute Force", "HIGH", &format!("line{}", i)));
+        }
+        let summary = digest.flush().unwrap();
+        assert_eq!(summary.matches("line").count(), 2);
+    }
+}
@@ -2,15 +2,37 @@ use async_trait::async_trait;
 use crate::models::SecurityAlert;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+use regex::Regex;
 use serde_json::json;
 use std::sync::Arc;
+use crate::config::FirewallConfig;
+use crate::digest::AlertDigest;
 use crate::ratelimiter::AlertRateLimiter;
+use crate::response::{extract_ip, IpsetAction, ResponseAction, ShellCommandAction};
+use crate::spool::AlertSpool;
 use tracing::{debug, error, info, warn};
 
 
 #[async_trait]
 pub trait AlertSink: Send + Sync {
     async fn send(&self, alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Stable identifier used to key per-sink delivery state in the alert spool.
+    fn name(&self) -> &'static str;
+}
+
+#[async_trait]
+impl<T: AlertSink + ?Sized> AlertSink for Arc<T> {
+    async fn send(&self, alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).send(alert).await
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
 }
 
 pub struct ConsoleSink;
@@ -21,21 +43,21 @@ impl AlertSink for ConsoleSink {
         info!(severity = %alert.severity, attack_type = %alert.attack_type, "ALERT dispatched");
         Ok(())
     }
+
+    fn name(&self) -> &'static str {
+        "console"
+    }
 }
 
 pub struct BffSink {
     pub url: String,
     pub token: String,
-    client: reqwest::Client,
+    client: Arc<reqwest::Client>,
 }
 
 impl BffSink {
-    pub fn new(url: String, token: String) -> Self {
-        Self {
-            url,
-            token,
-            client: reqwest::Client::new(),
-        }
+    pub fn new(url: String, token: String, client: Arc<reqwest::Client>) -> Self {
+        Self { url, token, client }
     }
 }
 
@@ -83,18 +105,22 @@ impl AlertSink for BffSink {
             tokio::time::sleep(tokio::time::Duration::from_millis(500 * attempts as u64)).await;
         }
     }
+
+    fn name(&self) -> &'static str {
+        "bff"
+    }
 }
 
 pub struct SlackSink {
     webhook_url: String,
-    client: reqwest::Client,
+    client: Arc<reqwest::Client>,
 }
 
 impl SlackSink {
-    pub fn new(webhook_url: &str) -> Self {
-        Self { 
+    pub fn new(webhook_url: &str, client: Arc<reqwest::Client>) -> Self {
+        Self {
             webhook_url: webhook_url.to_string(),
-            client: reqwest::Client::new(),
+            client,
         }
     }
 }
@@ -142,21 +168,25 @@ impl AlertSink for SlackSink {
             tokio::time::sleep(tokio::time::Duration::from_millis(500 * attempts as u64)).await;
         }
     }
+
+    fn name(&self) -> &'static str {
+        "slack"
+    }
 } 
 pub struct EmailSink {
     pub recipient: String,
     pub sender: String,
     pub api_url: String,
-    client: reqwest::Client, 
+    client: Arc<reqwest::Client>,
 }
 
 impl EmailSink {
-    pub fn new(recipient: String, sender: String, api_url: String) -> Self {
+    pub fn new(recipient: String, sender: String, api_url: String, client: Arc<reqwest::Client>) -> Self {
         Self {
             recipient,
             sender,
             api_url,
-            client: reqwest::Client::new(), 
+            client,
         }
     }
 }
@@ -214,6 +244,277 @@ impl AlertSink for EmailSink {
             tokio::time::sleep(tokio::time::Duration::from_millis(500 * attempts as u64)).await;
         }
     }
+
+    fn name(&self) -> &'static str {
+        "email"
+    }
+}
+
+/// Publishes each alert as JSON to a Redis Stream (`XADD`) or pub/sub
+/// channel, so multiple downstream consumers (dashboards, SIEM forwarders,
+/// other LogSentinel instances) can fan out off the alert feed independently.
+pub struct RedisSink {
+    client: redis::Client,
+    stream_key: String,
+    max_len: Option<usize>,
+    use_pubsub: bool,
+    channel: String,
+}
+
+impl RedisSink {
+    pub fn new(
+        redis_url: &str,
+        stream_key: String,
+        max_len: Option<usize>,
+        use_pubsub: bool,
+        channel: String,
+    ) -> Result<Self, redis::RedisError> {
+        Ok(Self {
+            client: redis::Client::open(redis_url)?,
+            stream_key,
+            max_len,
+            use_pubsub,
+            channel,
+        })
+    }
+
+    async fn publish_once(&self, payload: &str) -> redis::RedisResult<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        if self.use_pubsub {
+            let _: i64 = redis::cmd("PUBLISH")
+                .arg(&self.channel)
+                .arg(payload)
+                .query_async(&mut conn)
+                .await?;
+        } else {
+            let mut cmd = redis::cmd("XADD");
+            cmd.arg(&self.stream_key);
+            if let Some(max_len) = self.max_len {
+                cmd.arg("MAXLEN").arg("~").arg(max_len);
+            }
+            cmd.arg("*").arg("alert").arg(payload);
+            let _: String = cmd.query_async(&mut conn).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AlertSink for RedisSink {
+    async fn send(&self, alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
+        let payload = serde_json::to_string(alert)?;
+
+        let mut attempts = 0;
+        let max_retries = 3;
+
+        loop {
+            match self.publish_once(&payload).await {
+                Ok(()) => {
+                    debug!(stream = %self.stream_key, "RedisSink alert published successfully");
+                    return Ok(());
+                }
+                Err(e) => warn!(
+                    stream = %self.stream_key,
+                    error = %e,
+                    attempt = attempts + 1,
+                    max_retries,
+                    "RedisSink publish error"
+                ),
+            }
+
+            attempts += 1;
+            if attempts >= max_retries {
+                error!(stream = %self.stream_key, "RedisSink max retries exceeded");
+                return Err("Max retries exceeded for RedisSink".into());
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(500 * attempts as u64)).await;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+}
+
+/// Invokes a configurable external program per alert, piping the full
+/// `SecurityAlert` as JSON on stdin and mirroring key fields as env vars.
+/// Lets operators wire arbitrary integrations (ticketing, custom webhooks,
+/// firewall scripts) without touching this crate.
+pub struct ExecSink {
+    pub command: String,
+    pub args: Vec<String>,
+    pub timeout: std::time::Duration,
+}
+
+impl ExecSink {
+    pub fn new(command: String, args: Vec<String>, timeout_secs: u64) -> Self {
+        Self {
+            command,
+            args,
+            timeout: std::time::Duration::from_secs(timeout_secs),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for ExecSink {
+    async fn send(&self, alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
+        use std::process::Stdio;
+        use tokio::io::AsyncWriteExt;
+        use tokio::process::Command;
+
+        let payload = serde_json::to_vec(alert)?;
+
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .env("SEVERITY", &alert.severity)
+            .env("ATTACK_TYPE", &alert.attack_type)
+            .env("SOURCE_TYPE", &alert.source_type)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&payload).await?;
+        }
+
+        let run = async {
+            let output = child.wait_with_output().await?;
+            Ok::<_, Box<dyn std::error::Error>>(output)
+        };
+
+        let output = match tokio::time::timeout(self.timeout, run).await {
+            Ok(result) => result?,
+            Err(_) => {
+                error!(command = %self.command, "ExecSink command timed out, killing child process");
+                return Err(format!("exec sink command '{}' timed out after {:?}", self.command, self.timeout).into());
+            }
+        };
+
+        if !output.status.success() {
+            warn!(
+                command = %self.command,
+                status = %output.status,
+                stderr = %String::from_utf8_lossy(&output.stderr),
+                "ExecSink command exited with a non-zero status"
+            );
+            return Err(format!("exec sink command '{}' exited with {}", self.command, output.status).into());
+        }
+
+        debug!(command = %self.command, "ExecSink command completed successfully");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "exec"
+    }
+}
+
+/// Turns detection into intrusion prevention: extracts the source IP from
+/// each alert it sees and installs a drop rule (via `ResponseAction`, shared
+/// with the fail2ban-style active-response subsystem), with a TTL-based
+/// expiry and an in-memory dedup set so the same IP isn't re-banned on every
+/// alert. Unlike `ActiveResponse`, there's no offense threshold here — every
+/// alert routed to this sink is acted on immediately.
+pub struct FirewallSink {
+    ip_regex: Regex,
+    ban_duration: Duration,
+    action: Box<dyn ResponseAction>,
+    banned: DashMap<IpAddr, Instant>,
+    dry_run: bool,
+}
+
+impl FirewallSink {
+    pub fn new(config: &FirewallConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let pattern = config
+            .ip_regex
+            .clone()
+            .unwrap_or_else(|| r"(?:\d{1,3}\.){3}\d{1,3}|[0-9a-fA-F:]{3,}:[0-9a-fA-F:]+".to_string());
+
+        let action: Box<dyn ResponseAction> = if config.action.eq_ignore_ascii_case("ipset") {
+            Box::new(IpsetAction {
+                set_name: config.ipset_name.clone(),
+            })
+        } else {
+            Box::new(ShellCommandAction {
+                ban_template: config.shell_ban_command.clone(),
+                unban_template: config.shell_unban_command.clone(),
+            })
+        };
+
+        Ok(Self {
+            ip_regex: Regex::new(&pattern)?,
+            ban_duration: Duration::from_secs(config.ban_duration_secs),
+            action,
+            banned: DashMap::new(),
+            dry_run: config.dry_run,
+        })
+    }
+
+    /// Background task: periodically sweep for expired bans and unban them.
+    pub async fn run_expiry_loop(self: Arc<Self>, interval_secs: u64) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let expired: Vec<IpAddr> = self
+                .banned
+                .iter()
+                .filter(|e| *e.value() <= now)
+                .map(|e| *e.key())
+                .collect();
+
+            for ip in expired {
+                if self.dry_run {
+                    info!(ip = %ip, "FirewallSink (dry-run): would unban expired IP");
+                    self.banned.remove(&ip);
+                    continue;
+                }
+                if let Err(e) = self.action.unban(ip).await {
+                    error!(error = %e, ip = %ip, "FirewallSink failed to unban expired IP");
+                    continue;
+                }
+                self.banned.remove(&ip);
+                info!(ip = %ip, "FirewallSink: ban expired, unbanned");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for FirewallSink {
+    async fn send(&self, alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(ip) = extract_ip(&self.ip_regex, &alert.original_log) else {
+            debug!("FirewallSink found no IP in alert, nothing to ban");
+            return Ok(());
+        };
+
+        if let Some(expires_at) = self.banned.get(&ip) {
+            if *expires_at > Instant::now() {
+                debug!(ip = %ip, "FirewallSink: IP already banned, skipping duplicate rule");
+                return Ok(());
+            }
+        }
+
+        if self.dry_run {
+            info!(ip = %ip, duration_secs = self.ban_duration.as_secs(), "FirewallSink (dry-run): would ban IP");
+            self.banned.insert(ip, Instant::now() + self.ban_duration);
+            return Ok(());
+        }
+
+        self.action.ban(ip, self.ban_duration).await?;
+        self.banned.insert(ip, Instant::now() + self.ban_duration);
+        warn!(ip = %ip, duration_secs = self.ban_duration.as_secs(), "FirewallSink: banned attacker IP");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "firewall"
+    }
 }
 
 pub struct FileLoggerSink {
@@ -235,33 +536,99 @@ impl AlertSink for FileLoggerSink {
         debug!(path = %self.path, "Alert written to log file");
         Ok(())
     }
+
+    fn name(&self) -> &'static str {
+        "file"
+    }
 }
 
 pub struct Dispatcher {
     sinks: Arc<Vec<Box<dyn AlertSink>>>,
     rate_limiter: Arc<AlertRateLimiter>,
+    spool: Arc<AlertSpool>,
+    digest: Option<Arc<AlertDigest>>,
 }
 
 impl Dispatcher {
-    pub fn new(sinks: Arc<Vec<Box<dyn AlertSink>>>, rate_limiter: Arc<AlertRateLimiter>) -> Self {
+    pub fn new(sinks: Arc<Vec<Box<dyn AlertSink>>>, rate_limiter: Arc<AlertRateLimiter>, spool: Arc<AlertSpool>) -> Self {
         Self {
             sinks,
             rate_limiter,
+            spool,
+            digest: None,
         }
     }
 
+    /// Attach an `AlertDigest` so rate-limiter-suppressed (and, depending on
+    /// its configuration, all) alerts are folded into a periodic summary
+    /// instead of vanishing silently.
+    pub fn with_digest(mut self, digest: Arc<AlertDigest>) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Write the alert to the durable spool before any network call, make one
+    /// immediate best-effort delivery attempt per sink, and leave whatever
+    /// didn't ack for the background spool worker to retry with backoff.
     pub async fn dispatch(&self, alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
         let key = &alert.attack_type;
 
         if self.rate_limiter.check_alert(key) {
-            for sink in &*self.sinks {
-                if let Err(e) = sink.send(alert).await {
-                    error!(error = %e, "Failed to send alert to a destination");
+            if let Some(digest) = &self.digest {
+                if digest.record_all {
+                    digest.record(alert);
+                }
+            }
+
+            let sink_names: Vec<&str> = self.sinks.iter().map(|s| s.name()).collect();
+            match self.spool.enqueue(alert, &sink_names) {
+                Ok(entry) => {
+                    self.spool.attempt_delivery(entry, &self.sinks).await;
+                }
+                Err(e) => {
+                    error!(error = %e, "Failed to spool alert, dropping");
+                    return Err(e.into());
                 }
             }
         } else {
             warn!(key = %key, "Alert suppressed by rate limiter");
+            if let Some(digest) = &self.digest {
+                digest.record(alert);
+            }
         }
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_alert() -> SecurityAlert {
+        SecurityAlert {
+            timestamp: "2026-07-30T00:00:00Z".to_string(),
+            source_type: "generic".to_string(),
+            severity: "HIGH".to_string(),
+            attack_type: "SQLi".to_string(),
+            description: "test".to_string(),
+            original_log: "SELECT * FROM users".to_string(),
+        }
+    }
+
+    /// A child that outlives its timeout must actually be killed, not just
+    /// abandoned to keep running after `send()` returns an error.
+    #[tokio::test]
+    async fn test_exec_sink_kills_slow_child_on_timeout() {
+        let sink = ExecSink::new("sleep".to_string(), vec!["5".to_string()], 1);
+
+        let result = sink.send(&test_alert()).await;
+        assert!(result.is_err());
+
+        let status = tokio::process::Command::new("pgrep")
+            .args(["-f", "sleep 5"])
+            .status()
+            .await
+            .unwrap();
+        assert!(!status.success(), "slow child was not reaped after timeout");
+    }
 }
\ No newline at end of file
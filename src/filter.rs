@@ -1,17 +1,70 @@
-use crate::config::LogFilterConfig;
+use std::path::Path;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{error, info, warn};
+
+use crate::bayes::BayesianClassifier;
+use crate::config::{LogFilterConfig, SignatureType, SignaturesFile, ThreatSignature};
+
+/// Outcome of running a log line through the filter pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterVerdict {
+    /// Not suspicious by any signature, heuristic, or local classifier score.
+    Drop,
+    /// Ambiguous: forward to the LLM for a verdict.
+    Escalate,
+    /// The local Bayesian classifier is confident enough to alert without
+    /// spending an LLM call. Carries the raw `[0,1]` threat score.
+    Alert(f64),
+}
 
 pub struct LogFilter {
     config: LogFilterConfig,
+    /// Compiled-ish signature set, swapped atomically on hot-reload so
+    /// in-flight `classify()` calls never see a half-updated ruleset.
+    signatures: ArcSwap<Vec<ThreatSignature>>,
+    bayes: Option<BayesianClassifier>,
+    bayes_low_threshold: f64,
+    bayes_high_threshold: f64,
 }
 
 impl LogFilter {
     pub fn new(config: LogFilterConfig) -> Self {
-        Self { config }
+        let signatures = ArcSwap::from_pointee(config.signatures.clone());
+        Self {
+            config,
+            signatures,
+            bayes: None,
+            bayes_low_threshold: 0.0,
+            bayes_high_threshold: 1.0,
+        }
     }
 
-    pub fn is_suspicious(&self, line: &str) -> bool {
+    /// Attach a self-training Bayesian pre-filter so ambiguous lines can be
+    /// scored locally instead of always escalating to the LLM.
+    pub fn with_bayes(mut self, bayes_config: &crate::config::BayesConfig) -> Self {
+        if bayes_config.enabled {
+            self.bayes = Some(BayesianClassifier::new(
+                bayes_config.data_path.clone(),
+                bayes_config.smoothing_strength,
+                bayes_config.top_n_tokens,
+            ));
+            self.bayes_low_threshold = bayes_config.low_threshold;
+            self.bayes_high_threshold = bayes_config.high_threshold;
+        }
+        self
+    }
+
+    pub fn bayes(&self) -> Option<&BayesianClassifier> {
+        self.bayes.as_ref()
+    }
+
+    fn matches_signatures(&self, line: &str) -> bool {
         // Check signatures from the external file
-        for sig in &self.config.signatures {
+        let signatures = self.signatures.load();
+        for sig in signatures.iter() {
             match sig.sig_type {
                 crate::config::SignatureType::Exact => {
                     if line.contains(&sig.pattern) {
@@ -52,6 +105,102 @@ impl LogFilter {
 
         false
     }
+
+    pub fn is_suspicious(&self, line: &str) -> bool {
+        !matches!(self.classify(line), FilterVerdict::Drop)
+    }
+
+    /// Classify a line: signature/heuristic hits always escalate to the LLM
+    /// (they're not confident enough to alert on alone), everything else is
+    /// scored by the local Bayesian classifier when one is configured.
+    pub fn classify(&self, line: &str) -> FilterVerdict {
+        if self.matches_signatures(line) {
+            return FilterVerdict::Escalate;
+        }
+
+        if let Some(bayes) = &self.bayes {
+            let score = bayes.score(line);
+            if score < self.bayes_low_threshold {
+                return FilterVerdict::Drop;
+            }
+            if score > self.bayes_high_threshold {
+                return FilterVerdict::Alert(score);
+            }
+            return FilterVerdict::Escalate;
+        }
+
+        FilterVerdict::Drop
+    }
+
+    /// Feed an LLM verdict back into the local classifier so it keeps learning.
+    pub async fn train(&self, line: &str, is_threat: bool) {
+        if let Some(bayes) = &self.bayes {
+            bayes.train(line, is_threat).await;
+        }
+    }
+
+    /// Validate every pattern compiles, then atomically swap in the new
+    /// signature set. Rejects the whole batch on any bad regex so a typo'd
+    /// edit never wipes the live ruleset. Returns the number of signatures
+    /// now active.
+    pub fn reload_signatures(&self, new_signatures: Vec<ThreatSignature>) -> Result<usize, String> {
+        for sig in &new_signatures {
+            if sig.sig_type == SignatureType::Regex {
+                regex::Regex::new(&sig.pattern)
+                    .map_err(|e| format!("invalid regex in signature '{}': {}", sig.id, e))?;
+            }
+        }
+        let count = new_signatures.len();
+        self.signatures.store(Arc::new(new_signatures));
+        Ok(count)
+    }
+
+    /// Re-read and reload the signatures file at `path` (the same file
+    /// `Settings::new` parsed at startup).
+    pub fn reload_signatures_from_file(&self, path: &str) -> Result<usize, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("failed to read '{}': {}", path, e))?;
+        let parsed: SignaturesFile =
+            toml::from_str(&content).map_err(|e| format!("failed to parse '{}': {}", path, e))?;
+        self.reload_signatures(parsed.signatures)
+    }
+}
+
+/// Watches the signatures TOML file and hot-reloads `filter` on every
+/// modification, so an operator can edit threat patterns without restarting
+/// the daemon. A bad edit is logged and ignored, leaving the previous
+/// (still valid) ruleset in place.
+pub async fn watch_signatures_file(filter: Arc<LogFilter>, path: String) {
+    tokio::task::spawn_blocking(move || {
+        let (sync_tx, sync_rx) = std::sync::mpsc::channel();
+
+        let mut watcher = match RecommendedWatcher::new(sync_tx, NotifyConfig::default()) {
+            Ok(w) => w,
+            Err(e) => {
+                error!(error = ?e, "Failed to create signatures file watcher");
+                return;
+            }
+        };
+
+        if let Err(e) = watcher.watch(Path::new(&path), RecursiveMode::NonRecursive) {
+            error!(error = ?e, path = %path, "Failed to watch signatures file");
+            return;
+        }
+
+        info!(path = %path, "Watching signatures file for hot-reload");
+
+        for res in sync_rx {
+            match res {
+                Ok(event) if event.kind.is_modify() => match filter.reload_signatures_from_file(&path) {
+                    Ok(count) => info!(count, path = %path, "Hot-reloaded threat signatures"),
+                    Err(e) => error!(error = %e, path = %path, "Rejected signature reload, keeping previous ruleset"),
+                },
+                Ok(_) => {}
+                Err(e) => warn!(error = ?e, "Error watching signatures file"),
+            }
+        }
+    })
+    .await
+    .ok();
 }
 
 #[cfg(test)]
@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::config::Settings;
+use crate::watcher::LogWatcher;
+
+/// Produces raw log lines into the shared ingestion channel. `LogWatcher`
+/// (tailing a local file) is one implementation; network ingestors let
+/// LogSentinel collect logs pushed from remote hosts instead of only
+/// watching a single file on its own filesystem.
+#[async_trait]
+pub trait LogIngestor: Send + Sync {
+    async fn run(&self, tx: mpsc::Sender<String>) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Wraps the existing file-tailing watcher so it fits the `LogIngestor`
+/// trait alongside the network ingestors below.
+pub struct FileIngestor {
+    watcher: LogWatcher,
+}
+
+impl FileIngestor {
+    pub fn new(path: &str, checkpoint_dir: Option<&str>) -> Self {
+        let mut watcher = LogWatcher::new(path);
+        if let Some(dir) = checkpoint_dir {
+            watcher = watcher.with_checkpoint_dir(dir);
+        }
+        Self { watcher }
+    }
+}
+
+#[async_trait]
+impl LogIngestor for FileIngestor {
+    async fn run(&self, tx: mpsc::Sender<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.watcher.watch(tx).await.map_err(|e| e.into())
+    }
+}
+
+/// Accepts newline-delimited log lines over TCP from remote shippers
+/// (e.g. a syslog relay or a simple `nc`/forwarder script), one connection
+/// handled per task.
+pub struct TcpIngestor {
+    bind_addr: String,
+}
+
+impl TcpIngestor {
+    pub fn new(bind_addr: String) -> Self {
+        Self { bind_addr }
+    }
+}
+
+#[async_trait]
+impl LogIngestor for TcpIngestor {
+    async fn run(&self, tx: mpsc::Sender<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(&self.bind_addr).await?;
+        info!(addr = %self.bind_addr, "TCP log ingestor listening");
+
+        loop {
+            let (socket, peer) = listener.accept().await?;
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                debug!(peer = %peer, "TCP ingestor accepted connection");
+                let mut lines = BufReader::new(socket).lines();
+                loop {
+                    match lines.next_line().await {
+                        Ok(Some(line)) => {
+                            if tx.send(line).await.is_err() {
+                                warn!("Receiver dropped, closing TCP ingestor connection");
+                                return;
+                            }
+                        }
+                        Ok(None) => {
+                            debug!(peer = %peer, "TCP ingestor connection closed");
+                            return;
+                        }
+                        Err(e) => {
+                            error!(error = %e, peer = %peer, "Error reading from TCP ingestor connection");
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Subscribes to a ZeroMQ SUB socket and forwards each message as a log
+/// line. Runs the blocking `zmq` API on a dedicated blocking task, in the
+/// same style as the `notify`-based file watcher.
+pub struct ZmqSubIngestor {
+    endpoint: String,
+    topic: String,
+}
+
+impl ZmqSubIngestor {
+    pub fn new(endpoint: String, topic: String) -> Self {
+        Self { endpoint, topic }
+    }
+}
+
+#[async_trait]
+impl LogIngestor for ZmqSubIngestor {
+    async fn run(&self, tx: mpsc::Sender<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let endpoint = self.endpoint.clone();
+        let topic = self.topic.clone();
+
+        tokio::task::spawn_blocking(move || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            let ctx = zmq::Context::new();
+            let socket = ctx.socket(zmq::SUB)?;
+            socket.connect(&endpoint)?;
+            socket.set_subscribe(topic.as_bytes())?;
+            info!(endpoint = %endpoint, topic = %topic, "ZeroMQ log ingestor connected");
+
+            loop {
+                let message = socket.recv_string(0)?;
+                match message {
+                    Ok(line) => {
+                        if tx.blocking_send(line).is_err() {
+                            warn!("Receiver dropped, stopping ZeroMQ ingestor");
+                            return Ok(());
+                        }
+                    }
+                    Err(_) => warn!("Received non-UTF8 message on ZeroMQ ingestor, skipping"),
+                }
+            }
+        })
+        .await?
+        .map_err(|e| -> Box<dyn std::error::Error> { e.to_string().into() })
+    }
+}
+
+/// Construct the configured ingestor, keeping `main` oblivious to which
+/// transport is in use. `checkpoint_dir` (the `--data-dir`) is only
+/// relevant to the file ingestor.
+pub fn build_ingestor(settings: &Settings, checkpoint_dir: Option<&str>) -> Box<dyn LogIngestor> {
+    match settings.ingest.kind.as_str() {
+        "tcp" => Box::new(TcpIngestor::new(settings.ingest.tcp_bind_addr.clone())),
+        "zmq" => Box::new(ZmqSubIngestor::new(
+            settings.ingest.zmq_endpoint.clone(),
+            settings.ingest.zmq_topic.clone(),
+        )),
+        _ => Box::new(FileIngestor::new(&settings.log_path, checkpoint_dir)),
+    }
+}
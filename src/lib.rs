@@ -0,0 +1,18 @@
+pub mod aggregator;
+pub mod analyzer;
+pub mod bayes;
+pub mod config;
+pub mod control;
+pub mod digest;
+pub mod dispatcher;
+pub mod error;
+pub mod filter;
+pub mod ingest;
+pub mod llmprovider;
+pub mod metrics;
+pub mod models;
+pub mod ratelimiter;
+pub mod response;
+pub mod schema;
+pub mod spool;
+pub mod watcher;
@@ -1,20 +1,136 @@
 use async_trait::async_trait;
+use futures_util::stream::{self, Stream, StreamExt};
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use secrecy::{SecretString, ExposeSecret};
+use std::pin::Pin;
+use std::sync::Mutex;
 use crate::models::LogSource;
 use crate::config::Settings;
 use crate::error::AppError;
 
+/// A completed per-log verdict (or parse error) emitted mid-batch by
+/// `analyze_batch_stream`, as soon as its JSON object is fully received.
+pub type VerdictStream = Pin<Box<dyn Stream<Item = Result<Value, Box<dyn std::error::Error + Send + Sync>>> + Send>>;
+
+// Note: an agentic tool-calling loop (analyze_with_tools/ToolSpec/run_tool_loop,
+// letting a provider request a GeoIP/reverse-DNS/threat-intel lookup mid-verdict)
+// was prototyped here and then pulled back out — it had no executor
+// implementations to call (no GeoIP/DNS/IOC client exists in this crate) and
+// no caller in Agent::analyze/analyze_batch, so it was dead weight rather than
+// a usable feature. Descoped rather than merged half-finished; revisit once
+// there's an actual tool to wire up.
+
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     async fn analyze(&self, log_line: &str, source: &LogSource) -> Result<String, Box<dyn std::error::Error>>;
-    
+
     async fn analyze_batch(&self, log_lines: &[String], source: &LogSource) -> Result<String, Box<dyn std::error::Error>>;
 
+    /// Streams per-log verdicts as soon as each one is fully received,
+    /// instead of blocking until the whole batch response has generated.
+    /// Providers without native SSE support fall back to the blocking
+    /// `analyze_batch` call, replaying its results as a one-shot stream.
+    async fn analyze_batch_stream(&self, log_lines: &[String], source: &LogSource) -> VerdictStream {
+        let items: Vec<Result<Value, Box<dyn std::error::Error + Send + Sync>>> =
+            match self.analyze_batch(log_lines, source).await {
+                Ok(raw) => match serde_json::from_str::<Value>(&raw) {
+                    Ok(Value::Array(results)) => results.into_iter().map(Ok).collect(),
+                    Ok(Value::Object(mut obj)) if obj.get("results").map_or(false, Value::is_array) => {
+                        match obj.remove("results") {
+                            Some(Value::Array(results)) => results.into_iter().map(Ok).collect(),
+                            _ => Vec::new(),
+                        }
+                    }
+                    Ok(other) => vec![Ok(other)],
+                    Err(e) => vec![Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>)],
+                },
+                Err(e) => vec![Err(e.to_string().into())],
+            };
+        Box::pin(stream::iter(items))
+    }
+
     fn name(&self) -> String;
 }
 
+/// Incrementally extracts complete top-level `{...}` JSON objects from a
+/// streamed text buffer as soon as their closing brace arrives, so a
+/// per-log verdict can be emitted without waiting for the rest of the
+/// batch response to finish generating. Returns the objects found, plus
+/// the unconsumed tail of `buffer` to keep accumulating.
+fn drain_complete_objects(buffer: &str) -> (Vec<Value>, String) {
+    let mut objects = Vec::new();
+    let mut depth = 0i32;
+    let mut start = None;
+    let mut in_string = false;
+    let mut escape = false;
+    let mut last_consumed = 0;
+
+    for (i, ch) in buffer.char_indices() {
+        if in_string {
+            if escape {
+                escape = false;
+            } else if ch == '\\' {
+                escape = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(s) = start.take() {
+                        if let Ok(v) = serde_json::from_str::<Value>(&buffer[s..=i]) {
+                            objects.push(v);
+                        }
+                        last_consumed = i + 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (objects, buffer[last_consumed..].to_string())
+}
+
+/// Splits a chunked HTTP response body into a stream of raw lines, for
+/// providers using SSE (`data: ...`) or newline-delimited JSON streaming.
+fn line_stream(response: reqwest::Response) -> impl Stream<Item = String> {
+    async_stream::stream! {
+        let mut bytes = response.bytes_stream();
+        let mut buf = String::new();
+        while let Some(chunk) = bytes.next().await {
+            let chunk = match chunk {
+                Ok(c) => c,
+                Err(_) => break,
+            };
+            buf.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(pos) = buf.find('\n') {
+                let line = buf[..pos].trim().to_string();
+                buf.drain(..=pos);
+                if !line.is_empty() {
+                    yield line;
+                }
+            }
+        }
+        if !buf.trim().is_empty() {
+            yield buf.trim().to_string();
+        }
+    }
+}
+
 pub struct OllamaProvider {
     client: Client,
     model: String,
@@ -31,6 +147,66 @@ impl OllamaProvider {
     }
 }
 
+/// Adds to the `log_sentinel_tokens_{prompt,completion}_total` counters,
+/// labeled by provider and model. A silently-missing usage field (e.g. a
+/// provider that doesn't report token counts) just contributes zero.
+fn record_token_usage(provider: &str, model: &str, prompt_tokens: u64, completion_tokens: u64) {
+    if prompt_tokens > 0 {
+        crate::metrics::TOKENS_PROMPT
+            .with_label_values(&[provider, model])
+            .inc_by(prompt_tokens as f64);
+    }
+    if completion_tokens > 0 {
+        crate::metrics::TOKENS_COMPLETION
+            .with_label_values(&[provider, model])
+            .inc_by(completion_tokens as f64);
+    }
+}
+
+/// OpenAI (and its compatible wrappers) report usage as
+/// `usage.prompt_tokens`/`usage.completion_tokens` on every response.
+fn record_openai_usage(provider: &str, model: &str, response: &Value) {
+    record_token_usage(
+        provider,
+        model,
+        response["usage"]["prompt_tokens"].as_u64().unwrap_or(0),
+        response["usage"]["completion_tokens"].as_u64().unwrap_or(0),
+    );
+}
+
+/// Gemini reports usage as `usageMetadata.promptTokenCount`/`candidatesTokenCount`.
+fn record_gemini_usage(model: &str, response: &Value) {
+    record_token_usage(
+        "Gemini",
+        model,
+        response["usageMetadata"]["promptTokenCount"].as_u64().unwrap_or(0),
+        response["usageMetadata"]["candidatesTokenCount"].as_u64().unwrap_or(0),
+    );
+}
+
+/// Claude reports usage as `usage.input_tokens`/`usage.output_tokens`.
+fn record_claude_usage(model: &str, response: &Value) {
+    record_token_usage(
+        "Claude",
+        model,
+        response["usage"]["input_tokens"].as_u64().unwrap_or(0),
+        response["usage"]["output_tokens"].as_u64().unwrap_or(0),
+    );
+}
+
+/// Shared NULL/markdown-fence cleanup applied to the text a provider's
+/// response came back with.
+fn extract_final_verdict(content: &str) -> String {
+    if content.contains("NULL") {
+        return "NULL".to_string();
+    }
+    let cleaned = content.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
+    if serde_json::from_str::<Value>(cleaned).is_ok() {
+        return cleaned.to_string();
+    }
+    "NULL".to_string()
+}
+
 #[async_trait]
 impl LLMProvider for OllamaProvider {
     async fn analyze(&self, log_line: &str, source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
@@ -60,6 +236,13 @@ impl LLMProvider for OllamaProvider {
             .json::<Value>()
             .await?;
 
+        record_token_usage(
+            "Ollama",
+            &self.model,
+            response["prompt_eval_count"].as_u64().unwrap_or(0),
+            response["eval_count"].as_u64().unwrap_or(0),
+        );
+
         let content = response["response"].as_str().ok_or("No response field")?;
 
         if content.contains("NULL") {
@@ -110,24 +293,93 @@ impl LLMProvider for OllamaProvider {
             .json::<Value>()
             .await?;
 
+        record_token_usage(
+            "Ollama",
+            &self.model,
+            response["prompt_eval_count"].as_u64().unwrap_or(0),
+            response["eval_count"].as_u64().unwrap_or(0),
+        );
+
         Ok(response["response"].as_str().unwrap_or("[]").to_string())
     }
+
+    async fn analyze_batch_stream(&self, log_lines: &[String], source: &LogSource) -> VerdictStream {
+        let mut logs_formatted = String::new();
+        for (i, line) in log_lines.iter().enumerate() {
+            logs_formatted.push_str(&format!("{}. \"{}\"\n", i, line));
+        }
+
+        let prompt = format!(
+            "Act as a Senior SOC Analyst. Analyze these {} logs from {}:\n{}\n
+            Respond with a flat JSON object per log, back-to-back with NO array brackets
+            and NO enclosing object (e.g. {{\"index\":0,...}}{{\"index\":1,...}}), each with
+            'index' and either 'status': 'NULL' or 'severity'/'attack_type'/'description'.
+            Return ONLY valid JSON.",
+            log_lines.len(),
+            source.get_context(),
+            logs_formatted
+        );
+
+        let request = self.client.post(&self.api_url).json(&json!({
+            "model": &self.model,
+            "prompt": prompt,
+            "stream": true,
+        }));
+
+        stream_ndjson_field(request, "response")
+    }
+}
+
+/// Shared by providers whose streamed payload is newline-delimited JSON
+/// objects (not SSE), each carrying its incremental text under `field`
+/// (Ollama's `response` fragments).
+fn stream_ndjson_field(request: reqwest::RequestBuilder, field: &'static str) -> VerdictStream {
+    Box::pin(async_stream::stream! {
+        let response = match request.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                yield Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>);
+                return;
+            }
+        };
+
+        let mut lines = line_stream(response);
+        let mut buffer = String::new();
+        while let Some(line) = lines.next().await {
+            if let Ok(event) = serde_json::from_str::<Value>(&line) {
+                if let Some(fragment) = event[field].as_str() {
+                    buffer.push_str(fragment);
+                    let (objects, rest) = drain_complete_objects(&buffer);
+                    buffer = rest;
+                    for obj in objects {
+                        yield Ok(obj);
+                    }
+                }
+            }
+        }
+    })
 }
 
 pub struct OpenAiProvider {
     client: Client,
-    api_key: SecretString, 
+    api_key: SecretString,
     model: String,
     api_url: String, // Allow custom URL for OpenAI compatible APIs if needed, or default to standard
+    label: String, // Reported provider name; overridden by OpenAI-compatible wrappers (Mistral, Groq, Cohere)
 }
 
 impl OpenAiProvider {
     pub fn new(api_key: SecretString, model: &str, api_url: Option<String>) -> Self {
-        Self { 
+        Self::with_label(api_key, model, api_url, "OpenAI")
+    }
+
+    fn with_label(api_key: SecretString, model: &str, api_url: Option<String>, label: &str) -> Self {
+        Self {
             client: Client::new(),
-            api_key, 
+            api_key,
             model: model.to_string(),
-            api_url: api_url.unwrap_or("https://api.openai.com/v1/chat/completions".to_string())
+            api_url: api_url.unwrap_or("https://api.openai.com/v1/chat/completions".to_string()),
+            label: label.to_string(),
         }
     }
 }
@@ -160,6 +412,8 @@ impl LLMProvider for OpenAiProvider {
               .json::<Value>()
               .await?;
 
+      record_openai_usage(&self.label, &self.model, &response);
+
       // OpenAI response structure is different
       let content = response["choices"][0]["message"]["content"].as_str().ok_or("No content in response")?;
 
@@ -178,7 +432,7 @@ impl LLMProvider for OpenAiProvider {
     }
 
     fn name(&self) -> String {
-        "OpenAI".to_string()
+        self.label.clone()
     }
 
     async fn analyze_batch(&self, log_lines: &[String], source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
@@ -212,10 +466,85 @@ impl LLMProvider for OpenAiProvider {
               .json::<Value>()
               .await?;
 
+        record_openai_usage(&self.label, &self.model, &response);
+
         let content = response["choices"][0]["message"]["content"].as_str().ok_or("No content")?;
         let cleaned = content.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
         Ok(cleaned.to_string())
     }
+
+    async fn analyze_batch_stream(&self, log_lines: &[String], source: &LogSource) -> VerdictStream {
+        let mut logs_formatted = String::new();
+        for (i, line) in log_lines.iter().enumerate() {
+            logs_formatted.push_str(&format!("{}. \"{}\"\n", i, line));
+        }
+
+        let prompt = format!(
+            "Analyze these {} logs from {}:\n{}\n
+            Respond with a flat JSON object per log, back-to-back with NO array brackets
+            and NO enclosing object (e.g. {{\"index\":0,...}}{{\"index\":1,...}}).
+            Each object: 'index', 'severity', 'attack_type', 'description'.
+            If not a threat: {{'index': i, 'status': 'NULL'}}.",
+            log_lines.len(),
+            source.get_context(),
+            logs_formatted
+        );
+
+        let request = self.client.post(&self.api_url)
+            .header("Authorization", format!("Bearer {}", self.api_key.expose_secret()))
+            .json(&json!({
+                "model": &self.model,
+                "messages": [
+                    {"role": "system", "content": "You are a cybersecurity expert. Response in JSON format only (back-to-back flat objects, no array or wrapper)."},
+                    {"role": "user", "content": prompt}
+                ],
+                "temperature": 0,
+                "stream": true
+            }));
+
+        stream_sse_delta(request, |event| event["choices"][0]["delta"]["content"].as_str().map(str::to_string))
+    }
+}
+
+/// Shared by providers using SSE: reads `data: {json}` lines (ignoring the
+/// terminal `data: [DONE]` marker), pulls the incremental text out of each
+/// event via `extract_delta`, and emits each per-log JSON object as soon as
+/// its closing brace is parsed.
+fn stream_sse_delta(
+    request: reqwest::RequestBuilder,
+    extract_delta: impl Fn(&Value) -> Option<String> + Send + 'static,
+) -> VerdictStream {
+    Box::pin(async_stream::stream! {
+        let response = match request.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                yield Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>);
+                return;
+            }
+        };
+
+        let mut lines = line_stream(response);
+        let mut buffer = String::new();
+        while let Some(line) = lines.next().await {
+            let payload = match line.strip_prefix("data:") {
+                Some(p) => p.trim(),
+                None => continue,
+            };
+            if payload == "[DONE]" {
+                break;
+            }
+            if let Ok(event) = serde_json::from_str::<Value>(payload) {
+                if let Some(fragment) = extract_delta(&event) {
+                    buffer.push_str(&fragment);
+                    let (objects, rest) = drain_complete_objects(&buffer);
+                    buffer = rest;
+                    for obj in objects {
+                        yield Ok(obj);
+                    }
+                }
+            }
+        }
+    })
 }
 
 pub struct GeminiProvider {
@@ -264,6 +593,8 @@ impl LLMProvider for GeminiProvider {
             .json::<Value>()
             .await?;
 
+        record_gemini_usage(&self.model, &response);
+
         // Gemini response structure
         // { "candidates": [ { "content": { "parts": [ { "text": "..." } ] } } ] }
         let content = response["candidates"][0]["content"]["parts"][0]["text"].as_str()
@@ -307,10 +638,43 @@ impl LLMProvider for GeminiProvider {
             .json::<Value>()
             .await?;
 
+        record_gemini_usage(&self.model, &response);
+
         let content = response["candidates"][0]["content"]["parts"][0]["text"].as_str().ok_or("No content")?;
         let cleaned = content.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
         Ok(cleaned.to_string())
     }
+
+    async fn analyze_batch_stream(&self, log_lines: &[String], source: &LogSource) -> VerdictStream {
+        let mut logs_formatted = String::new();
+        for (i, line) in log_lines.iter().enumerate() {
+            logs_formatted.push_str(&format!("{}. \"{}\"\n", i, line));
+        }
+
+        let prompt = format!(
+            "Analyze these {} logs from {}:\n{}\n
+            Respond with a flat JSON object per log, back-to-back with NO array brackets
+            and NO enclosing object (e.g. {{\"index\":0,...}}{{\"index\":1,...}}).
+            Each object: 'index', 'severity', 'attack_type', 'description'.
+            If not a threat: {{'index': i, 'status': 'NULL'}}.",
+            log_lines.len(),
+            source.get_context(),
+            logs_formatted
+        );
+
+        let url = format!(
+            "{}/{}:streamGenerateContent?alt=sse&key={}",
+            self.api_url, self.model, self.api_key.expose_secret()
+        );
+        let request = self.client.post(&url).json(&json!({
+            "contents": [{ "parts": [{ "text": prompt }] }]
+        }));
+
+        stream_sse_delta(request, |event| {
+            event["candidates"][0]["content"]["parts"][0]["text"].as_str().map(str::to_string)
+        })
+    }
+
     fn name(&self) -> String {
         "Gemini".to_string()
     }
@@ -357,6 +721,8 @@ impl LLMProvider for ClaudeProvider {
             .json::<Value>()
             .await?;
 
+        record_claude_usage(&self.model, &response);
+
         let content = response["content"][0]["text"].as_str()
             .ok_or("No content in Claude response")?;
 
@@ -412,38 +778,604 @@ impl LLMProvider for ClaudeProvider {
             .json::<Value>()
             .await?;
 
+        record_claude_usage(&self.model, &response);
+
         let content = response["content"][0]["text"].as_str().ok_or("No content")?;
         let cleaned = content.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
         Ok(cleaned.to_string())
     }
+
+    async fn analyze_batch_stream(&self, log_lines: &[String], source: &LogSource) -> VerdictStream {
+        let mut logs_formatted = String::new();
+        for (i, line) in log_lines.iter().enumerate() {
+            logs_formatted.push_str(&format!("{}. \"{}\"\n", i, line));
+        }
+
+        let prompt = format!(
+            "Analyze these {} logs from {}:\n{}\n
+            Respond with a flat JSON object per log, back-to-back with NO array brackets
+            and NO enclosing object (e.g. {{\"index\":0,...}}{{\"index\":1,...}}).
+            Each object: 'index', 'severity', 'attack_type', 'description'.
+            If not a threat: {{'index': i, 'status': 'NULL'}}.",
+            log_lines.len(),
+            source.get_context(),
+            logs_formatted
+        );
+
+        let request = self.client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", self.api_key.expose_secret())
+            .header("anthropic-version", "2023-06-01")
+            .json(&json!({
+                "model": &self.model,
+                "max_tokens": 1024,
+                "messages": [{"role": "user", "content": prompt}],
+                "stream": true
+            }));
+
+        stream_sse_delta(request, |event| {
+            if event["type"] == "content_block_delta" {
+                event["delta"]["text"].as_str().map(str::to_string)
+            } else {
+                None
+            }
+        })
+    }
 }
 
-pub fn get_provider(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
-    let model = &settings.server.model;
-    let api_url = &settings.server.api_url;
-
-    let provider: Box<dyn LLMProvider> = match settings.server.provider.to_lowercase().as_str() {
-        "openai" => {
-            let api_key = settings.server.api_key.clone()
-                .ok_or_else(|| AppError::MissingApiKey("openai".into()))?;
-            Box::new(OpenAiProvider::new(api_key, model, api_url.clone()))
-        },
-        "gemini" => {
-            let api_key = settings.server.api_key.clone()
-                .ok_or_else(|| AppError::MissingApiKey("gemini".into()))?;
-            Box::new(GeminiProvider::new(api_key, model, api_url.clone()))
-        },
-        "claude" => {
-            let api_key = settings.server.api_key.clone()
-                .ok_or_else(|| AppError::MissingApiKey("claude".into()))?;
-            Box::new(ClaudeProvider::new(api_key, model))
-        },
-        _ => {
-            // Default to Ollama
-            let api_url_str = api_url.as_deref().unwrap_or("http://localhost:11434/api/generate");
-            Box::new(OllamaProvider::new(model, api_url_str))
+/// Replicate's API is asynchronous: creating a prediction only returns a
+/// pending object, so every call has to poll `urls.get` until the model
+/// finishes running.
+pub struct ReplicateProvider {
+    client: Client,
+    api_key: SecretString,
+    model: String,
+}
+
+impl ReplicateProvider {
+    pub fn new(api_key: SecretString, model: &str) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+
+    async fn predict(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+        const MAX_POLLS: u32 = 30;
+        const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+        let create_url = format!("https://api.replicate.com/v1/models/{}/predictions", self.model);
+        let prediction = self.client.post(&create_url)
+            .header("Authorization", format!("Bearer {}", self.api_key.expose_secret()))
+            .json(&json!({ "input": { "prompt": prompt } }))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+
+        let poll_url = prediction["urls"]["get"].as_str()
+            .ok_or("Replicate response missing urls.get")?
+            .to_string();
+
+        for _ in 0..MAX_POLLS {
+            let status_resp = self.client.get(&poll_url)
+                .header("Authorization", format!("Bearer {}", self.api_key.expose_secret()))
+                .send()
+                .await?
+                .json::<Value>()
+                .await?;
+
+            match status_resp["status"].as_str().unwrap_or("") {
+                "succeeded" => {
+                    let output = status_resp["output"].as_array()
+                        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>().join(""))
+                        .unwrap_or_default();
+                    return Ok(output);
+                }
+                "failed" | "canceled" => {
+                    return Err(format!("Replicate prediction {}", status_resp["status"].as_str().unwrap_or("failed")).into());
+                }
+                _ => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+
+        Err("Replicate prediction timed out".into())
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ReplicateProvider {
+    async fn analyze(&self, log_line: &str, source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+        let prompt = format!(
+            "Analyze this log of {}: \"{}\". If it is a threat, respond ONLY with a JSON object containing: \
+            'severity' (LOW, MEDIUM, HIGH, CRITICAL), 'attack_type', and 'description'. \
+            If it is NOT a threat, respond with the word 'NULL'.",
+            source.get_context(),
+            log_line
+        );
+        let content = self.predict(&prompt).await?;
+        Ok(extract_final_verdict(&content))
+    }
+
+    async fn analyze_batch(&self, log_lines: &[String], source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+        let mut logs_formatted = String::new();
+        for (i, line) in log_lines.iter().enumerate() {
+            logs_formatted.push_str(&format!("{}. \"{}\"\n", i, line));
+        }
+
+        let prompt = format!(
+            "Analyze these {} logs from {}:\n{}\n
+            Respond ONLY with a JSON array of objects.
+            Each object: 'index', 'severity', 'attack_type', 'description'.
+            If not a threat: {{'index': i, 'status': 'NULL'}}.",
+            log_lines.len(),
+            source.get_context(),
+            logs_formatted
+        );
+        let content = self.predict(&prompt).await?;
+        let cleaned = content.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
+        Ok(cleaned.to_string())
+    }
+
+    fn name(&self) -> String {
+        "Replicate".to_string()
+    }
+}
+
+/// One entry in a `MockProvider` fixture: the first rule whose `pattern`
+/// matches a log line wins, and its `response` is served back verbatim
+/// (either the string `"NULL"` or a verdict JSON object).
+#[derive(Clone, Debug, Deserialize)]
+struct MockRule {
+    #[serde(rename = "match")]
+    pattern: String,
+    /// When true, `pattern` is a regex; otherwise a plain substring check.
+    #[serde(default)]
+    regex: bool,
+    response: Value,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct MockFixture {
+    rules: Vec<MockRule>,
+}
+
+/// Offline provider driven entirely by a fixture file mapping log substrings
+/// or regexes to canned verdicts, for deterministic tests and dry runs over
+/// historical logs with zero API cost or rate-limit risk.
+pub struct MockProvider {
+    rules: Vec<MockRule>,
+}
+
+impl MockProvider {
+    pub fn new(fixture_path: &str) -> crate::error::Result<Self> {
+        let content = std::fs::read_to_string(fixture_path)?;
+        let fixture: MockFixture = serde_json::from_str(&content).map_err(|e| {
+            AppError::Provider(format!("Failed to parse mock fixture '{}': {}", fixture_path, e))
+        })?;
+        Ok(Self { rules: fixture.rules })
+    }
+
+    /// Returns the response of the first matching rule, serialized back to a
+    /// JSON string, or `"NULL"` if nothing matches.
+    fn lookup(&self, log_line: &str) -> String {
+        for rule in &self.rules {
+            let matched = if rule.regex {
+                regex::Regex::new(&rule.pattern).map(|re| re.is_match(log_line)).unwrap_or(false)
+            } else {
+                log_line.contains(&rule.pattern)
+            };
+            if matched {
+                return match &rule.response {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+            }
+        }
+        "NULL".to_string()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MockProvider {
+    async fn analyze(&self, log_line: &str, _source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.lookup(log_line))
+    }
+
+    async fn analyze_batch(&self, log_lines: &[String], _source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+        let mut results = Vec::new();
+        for (i, line) in log_lines.iter().enumerate() {
+            let verdict = self.lookup(line);
+            if verdict == "NULL" {
+                results.push(json!({"index": i, "status": "NULL"}));
+            } else if let Ok(mut v) = serde_json::from_str::<Value>(&verdict) {
+                v["index"] = json!(i);
+                results.push(v);
+            }
+        }
+        Ok(Value::Array(results).to_string())
+    }
+
+    fn name(&self) -> String {
+        "Mock".to_string()
+    }
+}
+
+/// One captured `(prompt, raw_response)` pair, where `prompt` is the log
+/// content a provider call was keyed on (the single log line for `analyze`,
+/// or the batch's lines joined by newline for `analyze_batch`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RecordedPair {
+    prompt: String,
+    raw_response: String,
+}
+
+/// Wraps another provider to capture every call it serves (`"record"` mode)
+/// or serves previously-captured responses without an inner provider at all
+/// (`"replay"` mode), so a pipeline run can be replayed deterministically
+/// offline. Each capture is flushed to `fixture_path` immediately so a crash
+/// mid-recording doesn't lose prior captures.
+pub struct RecordReplayProvider {
+    inner: Option<Box<dyn LLMProvider>>,
+    fixture_path: String,
+    recorded: Mutex<Vec<RecordedPair>>,
+}
+
+impl RecordReplayProvider {
+    pub fn new_record(inner: Box<dyn LLMProvider>, fixture_path: &str) -> Self {
+        Self {
+            inner: Some(inner),
+            fixture_path: fixture_path.to_string(),
+            recorded: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn new_replay(fixture_path: &str) -> crate::error::Result<Self> {
+        let content = std::fs::read_to_string(fixture_path)?;
+        let recorded: Vec<RecordedPair> = serde_json::from_str(&content).map_err(|e| {
+            AppError::Provider(format!("Failed to parse record/replay fixture '{}': {}", fixture_path, e))
+        })?;
+        Ok(Self {
+            inner: None,
+            fixture_path: fixture_path.to_string(),
+            recorded: Mutex::new(recorded),
+        })
+    }
+
+    fn replay(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+        self.recorded
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|pair| pair.prompt == prompt)
+            .map(|pair| pair.raw_response.clone())
+            .ok_or_else(|| format!("No recorded response for prompt in replay mode: {:?}", prompt).into())
+    }
+
+    fn record(&self, prompt: String, raw_response: String) {
+        let mut recorded = self.recorded.lock().unwrap();
+        recorded.push(RecordedPair { prompt, raw_response });
+        if let Ok(serialized) = serde_json::to_string_pretty(&*recorded) {
+            let _ = std::fs::write(&self.fixture_path, serialized);
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RecordReplayProvider {
+    async fn analyze(&self, log_line: &str, source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+        match &self.inner {
+            Some(inner) => {
+                let raw = inner.analyze(log_line, source).await?;
+                self.record(log_line.to_string(), raw.clone());
+                Ok(raw)
+            }
+            None => self.replay(log_line),
+        }
+    }
+
+    async fn analyze_batch(&self, log_lines: &[String], source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+        let key = log_lines.join("\n");
+        match &self.inner {
+            Some(inner) => {
+                let raw = inner.analyze_batch(log_lines, source).await?;
+                self.record(key, raw.clone());
+                Ok(raw)
+            }
+            None => self.replay(&key),
+        }
+    }
+
+    fn name(&self) -> String {
+        match &self.inner {
+            Some(inner) => format!("{} (recording)", inner.name()),
+            None => "Replay".to_string(),
+        }
+    }
+}
+
+/// Thin wrapper around an `OpenAiProvider` for backends that speak the same
+/// request/response shape (chat completions with `choices[0].message.content`)
+/// but have their own default endpoint. Only the default `api_url` and the
+/// reported provider name differ.
+macro_rules! openai_compatible_provider {
+    ($name:ident, $label:expr, $default_url:expr) => {
+        pub struct $name(OpenAiProvider);
+
+        impl $name {
+            pub fn new(api_key: SecretString, model: &str, api_url: Option<String>) -> Self {
+                Self(OpenAiProvider::with_label(
+                    api_key,
+                    model,
+                    Some(api_url.unwrap_or_else(|| $default_url.to_string())),
+                    $label,
+                ))
+            }
+        }
+
+        #[async_trait]
+        impl LLMProvider for $name {
+            async fn analyze(&self, log_line: &str, source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+                self.0.analyze(log_line, source).await
+            }
+
+            async fn analyze_batch(&self, log_lines: &[String], source: &LogSource) -> Result<String, Box<dyn std::error::Error>> {
+                self.0.analyze_batch(log_lines, source).await
+            }
+
+            async fn analyze_batch_stream(&self, log_lines: &[String], source: &LogSource) -> VerdictStream {
+                self.0.analyze_batch_stream(log_lines, source).await
+            }
+
+            fn name(&self) -> String {
+                self.0.name()
+            }
         }
     };
+}
+
+openai_compatible_provider!(MistralProvider, "Mistral", "https://api.mistral.ai/v1/chat/completions");
+openai_compatible_provider!(GroqProvider, "Groq", "https://api.groq.com/openai/v1/chat/completions");
+openai_compatible_provider!(CohereProvider, "Cohere", "https://api.cohere.ai/compatibility/v1/chat/completions");
+
+type ProviderFactory = fn(&Settings) -> crate::error::Result<Box<dyn LLMProvider>>;
+
+fn build_openai(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "openai")?;
+    Ok(Box::new(OpenAiProvider::new(api_key, &settings.server.model, settings.server.api_url.clone())))
+}
+
+fn build_gemini(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "gemini")?;
+    Ok(Box::new(GeminiProvider::new(api_key, &settings.server.model, settings.server.api_url.clone())))
+}
+
+fn build_claude(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "claude")?;
+    Ok(Box::new(ClaudeProvider::new(api_key, &settings.server.model)))
+}
+
+fn build_replicate(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "replicate")?;
+    Ok(Box::new(ReplicateProvider::new(api_key, &settings.server.model)))
+}
+
+fn build_mistral(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "mistral")?;
+    Ok(Box::new(MistralProvider::new(api_key, &settings.server.model, settings.server.api_url.clone())))
+}
+
+fn build_groq(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "groq")?;
+    Ok(Box::new(GroqProvider::new(api_key, &settings.server.model, settings.server.api_url.clone())))
+}
+
+fn build_cohere(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_key = require_api_key(settings, "cohere")?;
+    Ok(Box::new(CohereProvider::new(api_key, &settings.server.model, settings.server.api_url.clone())))
+}
+
+fn build_ollama(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    let api_url = settings.server.api_url.as_deref().unwrap_or("http://localhost:11434/api/generate");
+    Ok(Box::new(OllamaProvider::new(&settings.server.model, api_url)))
+}
+
+fn build_mock(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    Ok(Box::new(MockProvider::new(&settings.mock.fixture_path)?))
+}
 
-    Ok(provider)
+fn require_api_key(settings: &Settings, provider: &str) -> crate::error::Result<SecretString> {
+    settings.server.api_key.clone().ok_or_else(|| AppError::MissingApiKey(provider.into()))
+}
+
+/// Registry of provider constructors keyed by the `settings.server.provider`
+/// name, so adding a new OpenAI-compatible or self-hosted backend is a
+/// one-line addition here rather than a change to the factory's control flow.
+fn provider_registry() -> &'static [(&'static str, ProviderFactory)] {
+    &[
+        ("openai", build_openai as ProviderFactory),
+        ("gemini", build_gemini as ProviderFactory),
+        ("claude", build_claude as ProviderFactory),
+        ("replicate", build_replicate as ProviderFactory),
+        ("mistral", build_mistral as ProviderFactory),
+        ("groq", build_groq as ProviderFactory),
+        ("cohere", build_cohere as ProviderFactory),
+        ("ollama", build_ollama as ProviderFactory),
+        ("mock", build_mock as ProviderFactory),
+    ]
+}
+
+/// Resolves `settings.server.provider` through the registry (falling back to
+/// Ollama for unrecognized names, matching the previous factory's behavior),
+/// then applies `settings.mock.mode`: `"record"` wraps the resolved provider
+/// so every call it serves is captured to `settings.mock.fixture_path`;
+/// `"replay"` skips building a live provider entirely and serves only
+/// previously-captured responses.
+pub fn get_provider(settings: &Settings) -> crate::error::Result<Box<dyn LLMProvider>> {
+    if settings.mock.mode == "replay" {
+        return Ok(Box::new(RecordReplayProvider::new_replay(&settings.mock.fixture_path)?));
+    }
+
+    let key = settings.server.provider.to_lowercase();
+    let provider = provider_registry()
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, factory)| factory(settings))
+        .unwrap_or_else(|| build_ollama(settings))?;
+
+    if settings.mock.mode == "record" {
+        Ok(Box::new(RecordReplayProvider::new_record(provider, &settings.mock.fixture_path)))
+    } else {
+        Ok(provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Simulates the streaming prompts' flat, back-to-back object format
+    /// (no array/`results` wrapper) arriving in arbitrary chunks, the same
+    /// way `stream_ndjson_field`/`stream_sse_delta` feed fragments into
+    /// `drain_complete_objects` as they come off the wire.
+    #[test]
+    fn test_drain_complete_objects_flat_sequence() {
+        let mut buffer = String::new();
+        let mut all_objects = Vec::new();
+
+        for chunk in [
+            r#"{"index":0,"status":"NULL"}{"index":1,"sever"#,
+            r#"ity":"HIGH","attack_type":"SQLi","description":"d"}{"inde"#,
+            r#"x":2,"status":"NULL"}"#,
+        ] {
+            buffer.push_str(chunk);
+            let (objects, rest) = drain_complete_objects(&buffer);
+            buffer = rest;
+            all_objects.extend(objects);
+        }
+
+        assert_eq!(all_objects.len(), 3);
+        assert_eq!(all_objects[0]["index"], 0);
+        assert_eq!(all_objects[0]["status"], "NULL");
+        assert_eq!(all_objects[1]["index"], 1);
+        assert_eq!(all_objects[1]["severity"], "HIGH");
+        assert_eq!(all_objects[2]["index"], 2);
+        assert!(buffer.is_empty());
+    }
+
+    /// Regression guard for the envelope-shaped bug: a `{"results": [...]}`
+    /// blob only closes its outer brace once, at the very end, so it must
+    /// never be mistaken for multiple per-log verdicts by the scanner the
+    /// streaming prompts are designed around.
+    #[test]
+    fn test_drain_complete_objects_envelope_yields_single_object() {
+        let buffer = r#"{"results":[{"index":0,"status":"NULL"},{"index":1,"severity":"HIGH"}]}"#;
+        let (objects, rest) = drain_complete_objects(buffer);
+
+        assert_eq!(objects.len(), 1);
+        assert!(objects[0]["index"].is_null());
+        assert!(objects[0]["results"].is_array());
+        assert!(rest.is_empty());
+    }
+
+    fn write_fixture(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_lookup_substring_and_regex() {
+        let path = write_fixture(
+            "mock-fixture-lookup",
+            r#"{"rules": [
+                {"match": "SELECT", "response": {"severity": "HIGH", "attack_type": "SQLi", "description": "SQL injection"}},
+                {"match": "^GET /health", "regex": true, "response": "NULL"}
+            ]}"#,
+        );
+        let provider = MockProvider::new(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let verdict = provider.analyze("SELECT * FROM users", &LogSource::Generic).await.unwrap();
+        let parsed: Value = serde_json::from_str(&verdict).unwrap();
+        assert_eq!(parsed["attack_type"], "SQLi");
+
+        let null_verdict = provider.analyze("GET /health", &LogSource::Generic).await.unwrap();
+        assert_eq!(null_verdict, "NULL");
+
+        let unmatched = provider.analyze("totally unrelated line", &LogSource::Generic).await.unwrap();
+        assert_eq!(unmatched, "NULL");
+    }
+
+    #[tokio::test]
+    async fn test_record_replay_round_trip() {
+        let fixture_path = write_fixture("record-replay-fixture", "[]");
+        std::fs::remove_file(&fixture_path).ok();
+
+        let mock = Box::new(MockProvider { rules: vec![] });
+        // Real rule content doesn't matter here: `analyze` always falls through
+        // to "NULL" for an empty rule set, which is enough to prove recording works.
+        let recorder = RecordReplayProvider::new_record(mock, &fixture_path);
+        let raw = recorder.analyze("GET /index.html", &LogSource::Nginx).await.unwrap();
+        assert_eq!(raw, "NULL");
+
+        let replayer = RecordReplayProvider::new_replay(&fixture_path).unwrap();
+        let replayed = replayer.analyze("GET /index.html", &LogSource::Nginx).await.unwrap();
+        assert_eq!(replayed, "NULL");
+
+        let miss = replayer.analyze("never recorded", &LogSource::Nginx).await;
+        assert!(miss.is_err());
+
+        std::fs::remove_file(&fixture_path).ok();
+    }
+
+    /// Exercises the pipeline the request describes: a deterministic
+    /// `MockProvider` feeds `Agent::analyze_batch`, and the resulting alerts
+    /// are compared structurally (not byte-for-byte) against what the fixture
+    /// should produce.
+    #[tokio::test]
+    async fn test_mock_pipeline_analyze_to_alerts() {
+        let path = write_fixture(
+            "mock-fixture-pipeline",
+            r#"{"rules": [
+                {"match": "SELECT", "response": {"severity": "HIGH", "attack_type": "SQLi", "description": "SQL injection"}},
+                {"match": "<script>", "response": {"severity": "MEDIUM", "attack_type": "XSS", "description": "Cross-site scripting"}}
+            ]}"#,
+        );
+        let provider = MockProvider::new(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let agent = crate::analyzer::Agent::new(Box::new(provider), "mock-model");
+        let lines = vec![
+            "SELECT * FROM users".to_string(),
+            "GET /normal".to_string(),
+            "<script>alert(1)</script>".to_string(),
+        ];
+
+        let alerts = agent.analyze_batch(&lines, &LogSource::Generic).await;
+
+        assert_eq!(alerts.len(), 2);
+        let actual: Vec<Value> = alerts.iter().map(|a| json!({
+            "severity": a.severity,
+            "attack_type": a.attack_type,
+            "description": a.description,
+            "original_log": a.original_log,
+        })).collect();
+        let expected = vec![
+            json!({
+                "severity": "HIGH",
+                "attack_type": "SQLi",
+                "description": "SQL injection",
+                "original_log": "SELECT * FROM users",
+            }),
+            json!({
+                "severity": "MEDIUM",
+                "attack_type": "XSS",
+                "description": "Cross-site scripting",
+                "original_log": "<script>alert(1)</script>",
+            }),
+        ];
+        assert_eq!(actual, expected);
+    }
 }
@@ -1,13 +1,18 @@
 use tokio::sync::mpsc;
+use futures_util::StreamExt;
 use log_sentinel::config::Settings;
 use log_sentinel::analyzer::Agent;
-use log_sentinel::filter::LogFilter;
-use log_sentinel::watcher::LogWatcher;
-use log_sentinel::models::LogSource;
-use log_sentinel::dispatcher::{AlertSink, BffSink, EmailSink, FileLoggerSink, Dispatcher};
+use log_sentinel::filter::{watch_signatures_file, FilterVerdict, LogFilter};
+use log_sentinel::ingest::{build_ingestor, LogIngestor};
+use log_sentinel::models::{LogSource, SecurityAlert};
+use log_sentinel::dispatcher::{AlertSink, BffSink, EmailSink, ExecSink, FileLoggerSink, FirewallSink, RedisSink, Dispatcher};
 use log_sentinel::ratelimiter::AlertRateLimiter;
 use log_sentinel::llmprovider::{LLMProvider, get_provider};
 use log_sentinel::aggregator::LogAggregator;
+use log_sentinel::digest::AlertDigest;
+use log_sentinel::response::{ActiveResponse, IpsetAction, ResponseAction, ShellCommandAction};
+use log_sentinel::spool::AlertSpool;
+use log_sentinel::control::{run_control_server, ControlState};
 use log_sentinel::metrics::REGISTRY;
 use std::sync::Arc;
 use tracing::{debug, error, info, warn};
@@ -17,6 +22,7 @@ use axum::{routing::get, Router};
 use prometheus::{Encoder, TextEncoder};
 use clap::Parser;
 use daemonize::Daemonize;
+use fs2::FileExt;
 use std::fs::File;
 
 #[derive(Parser, Debug)]
@@ -30,6 +36,33 @@ struct Args {
 
     #[arg(long)]
     api_key_file: Option<String>,
+
+    /// Directory holding this instance's PID file, lock file, watcher
+    /// checkpoint, and alert spool. One `--data-dir` fully identifies one
+    /// running daemon.
+    #[arg(long, default_value = "/var/lib/log_sentinel")]
+    data_dir: String,
+}
+
+/// Acquires an exclusive advisory lock on `<data_dir>/log_sentinel.lock`.
+/// Returns the open `File` so the caller can hold it for the process
+/// lifetime (dropping it releases the lock). Exits the process if another
+/// instance already holds it.
+fn acquire_instance_lock(data_dir: &str) -> std::io::Result<File> {
+    std::fs::create_dir_all(data_dir)?;
+    let lock_path = std::path::Path::new(data_dir).join("log_sentinel.lock");
+    let file = File::create(&lock_path)?;
+
+    if let Err(e) = file.try_lock_exclusive() {
+        eprintln!(
+            "log_sentinel: another instance already holds the lock at {} ({})",
+            lock_path.display(),
+            e
+        );
+        std::process::exit(1);
+    }
+
+    Ok(file)
 }
 
 #[tokio::main]
@@ -44,8 +77,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let stdout = File::create("/tmp/log_sentinel.out")?;
         let stderr = File::create("/tmp/log_sentinel.err")?;
 
+        let pid_path = std::path::Path::new(&args.data_dir).join("log_sentinel.pid");
+        std::fs::create_dir_all(&args.data_dir)?;
+
         let daemonize = Daemonize::new()
-            .pid_file("/tmp/log_sentinel.pid")
+            .pid_file(pid_path)
             .chown_pid_file(true)
             .working_directory(".")
             .stdout(stdout)
@@ -57,7 +93,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    let settings = Settings::new(args.config.as_deref(), args.api_key_file)?;
+    // Held for the lifetime of the process: dropping it releases the lock.
+    let _instance_lock = acquire_instance_lock(&args.data_dir)?;
+
+    let mut settings = Settings::new(args.config.as_deref(), args.api_key_file)?;
+    settings.spool.dir = std::path::Path::new(&args.data_dir)
+        .join("spool")
+        .to_string_lossy()
+        .to_string();
     let log_path = settings.log_path.clone();
     let source = settings.source.clone();
     
@@ -83,18 +126,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let rate_limiter = Arc::new(AlertRateLimiter::new(&settings.rats)?);
     let dispatcher_rate_limiter = Arc::clone(&rate_limiter);
 
+    const ANALYSIS_CHANNEL_CAPACITY: usize = 1_000;
+
     let (tx, rx) = mpsc::channel(10_000);
-    let (analysis_tx, mut analysis_rx) = mpsc::channel(1_000);
+    let (analysis_tx, mut analysis_rx) = mpsc::channel(ANALYSIS_CHANNEL_CAPACITY);
 
     let provider: Box<dyn LLMProvider> = get_provider(&settings)?;
 
     // Agent holds the provider (Box<dyn LLMProvider>) so we wrap Agent in Arc
-    let agent = Arc::new(Agent::new(provider));
-    let watcher = LogWatcher::new(&log_path);
-    let filter = Arc::new(LogFilter::new(settings.filter.clone()));
-    
-    // Spawn watcher with exponential backoff and retry limit
-    let watcher_clone = watcher.clone();
+    let agent = Arc::new(Agent::new(provider, settings.server.model.clone()));
+    let ingestor: Arc<dyn LogIngestor> = Arc::from(build_ingestor(&settings, Some(&args.data_dir)));
+    let filter = Arc::new(LogFilter::new(settings.filter.clone()).with_bayes(&settings.bayes));
+
+    let signatures_watch_filter = Arc::clone(&filter);
+    let signatures_path = settings.filter.signatures_path.clone();
+    tokio::spawn(watch_signatures_file(signatures_watch_filter, signatures_path));
+
+    // Spawn the configured ingestor with exponential backoff and retry limit
+    let ingestor_clone = Arc::clone(&ingestor);
     let tx_clone = tx.clone();
     tokio::spawn(async move {
         const MAX_RETRIES: u32 = 5;
@@ -104,17 +153,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let mut consecutive_failures: u32 = 0;
 
         loop {
-            let watcher = watcher_clone.clone();
+            let ingestor = Arc::clone(&ingestor_clone);
             let tx = tx_clone.clone();
 
             let task = tokio::spawn(async move {
-                watcher.watch(tx).await
+                ingestor.run(tx).await
             });
 
             match task.await {
                 Ok(Ok(())) => {
                     // Clean exit: reset failure counter and restart quickly
-                    info!("Watcher exited cleanly, restarting in {}s", BASE_DELAY_SECS);
+                    info!("Ingestor exited cleanly, restarting in {}s", BASE_DELAY_SECS);
                     consecutive_failures = 0;
                     tokio::time::sleep(tokio::time::Duration::from_secs(BASE_DELAY_SECS)).await;
                 }
@@ -124,7 +173,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         error!(
                             error = ?e,
                             consecutive_failures,
-                            "Watcher failed too many times, giving up"
+                            "Ingestor failed too many times, giving up"
                         );
                         break;
                     }
@@ -134,7 +183,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         attempt = consecutive_failures,
                         max = MAX_RETRIES,
                         delay_secs = delay,
-                        "Watcher failed, retrying with backoff"
+                        "Ingestor failed, retrying with backoff"
                     );
                     tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
                 }
@@ -144,7 +193,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         error!(
                             error = ?e,
                             consecutive_failures,
-                            "Watcher panicked too many times, giving up"
+                            "Ingestor panicked too many times, giving up"
                         );
                         break;
                     }
@@ -154,7 +203,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         attempt = consecutive_failures,
                         max = MAX_RETRIES,
                         delay_secs = delay,
-                        "Watcher panicked, retrying with backoff"
+                        "Ingestor panicked, retrying with backoff"
                     );
                     tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
                 }
@@ -170,13 +219,100 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         300 // 300ms timeout
     );
 
-    let sinks: Arc<Vec<Box<dyn AlertSink>>> = Arc::new(create_sinks(&settings));
+    let mut http_client_builder = reqwest::Client::builder()
+        .timeout(tokio::time::Duration::from_secs(settings.http.timeout_secs))
+        .pool_max_idle_per_host(settings.http.pool_max_idle_per_host);
+    if let Some(proxy_url) = &settings.http.proxy {
+        http_client_builder = http_client_builder.proxy(reqwest::Proxy::all(proxy_url)?);
+    }
+    let http_client = Arc::new(http_client_builder.build()?);
+
+    let sinks: Arc<Vec<Box<dyn AlertSink>>> = Arc::new(create_sinks(&settings, Arc::clone(&http_client)));
+    let spool = Arc::new(AlertSpool::new(
+        settings.spool.dir.clone(),
+        settings.spool.max_age_secs,
+        settings.spool.quota,
+        settings.spool.max_attempts,
+    )?);
+    let mut dispatcher_builder = Dispatcher::new(Arc::clone(&sinks), Arc::clone(&dispatcher_rate_limiter), Arc::clone(&spool));
+    if settings.digest.enabled {
+        let digest = Arc::new(AlertDigest::new(settings.digest.max_samples, settings.digest.record_all));
+        let digest_flush = Arc::clone(&digest);
+        let digest_sinks = Arc::clone(&sinks);
+        let flush_interval = settings.digest.flush_interval_secs;
+        tokio::spawn(async move {
+            digest_flush.run_flush_loop(digest_sinks, flush_interval).await;
+        });
+        dispatcher_builder = dispatcher_builder.with_digest(digest);
+    }
+    let dispatcher = Arc::new(dispatcher_builder);
+
+    // Resume any alerts left over from a previous run and keep retrying
+    // whatever the immediate dispatch attempt didn't manage to deliver.
+    let spool_worker = Arc::clone(&spool);
+    let sinks_worker = Arc::clone(&sinks);
+    let sweep_interval = settings.spool.sweep_interval_secs;
+    tokio::spawn(async move {
+        spool_worker.run_worker(sinks_worker, sweep_interval).await;
+    });
+
+    let active_response = if settings.response.enabled {
+        let action = build_response_action(&settings);
+        match ActiveResponse::new(&settings.response, action) {
+            Ok(response) => {
+                let response = Arc::new(response);
+                response.restore().await;
+                let expiry_response = Arc::clone(&response);
+                let sweep_secs = settings.response.expiry_sweep_secs;
+                tokio::spawn(async move {
+                    expiry_response.run_expiry_loop(sweep_secs).await;
+                });
+                Some(response)
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to initialize active-response subsystem, continuing without it");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let (reload_tx, mut reload_rx) = mpsc::channel::<()>(1);
+    let reload_filter = Arc::clone(&filter);
+    let reload_path = settings.filter.signatures_path.clone();
+    tokio::spawn(async move {
+        while reload_rx.recv().await.is_some() {
+            match reload_filter.reload_signatures_from_file(&reload_path) {
+                Ok(count) => info!(count, "Signatures reloaded via control socket"),
+                Err(e) => error!(error = %e, "Signature reload requested over control socket was rejected"),
+            }
+        }
+    });
+
+    let control_state = Arc::new(ControlState::new(
+        settings.control.recent_alerts_capacity,
+        analysis_tx.clone(),
+        ANALYSIS_CHANNEL_CAPACITY,
+        active_response.clone(),
+        Arc::clone(&spool),
+        reload_tx,
+    ));
+    if settings.control.enabled {
+        let control_state = Arc::clone(&control_state);
+        let bind_addr = settings.control.bind_addr.clone();
+        tokio::spawn(async move {
+            run_control_server(control_state, bind_addr).await;
+        });
+    }
 
     // Spawn Analysis Batcher
     let agent_batch = Arc::clone(&agent);
-    let sinks_batch = Arc::clone(&sinks);
-    let rate_limiter_batch = Arc::clone(&dispatcher_rate_limiter);
+    let dispatcher_batch = Arc::clone(&dispatcher);
     let source_batch = source.clone();
+    let filter_batch = Arc::clone(&filter);
+    let active_response_batch = active_response.clone();
+    let control_state_batch = Arc::clone(&control_state);
     let batch_size = settings.analysis.batch_size;
     let batch_timeout = settings.analysis.batch_timeout_ms;
 
@@ -192,13 +328,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Some(log) = analysis_rx.recv() => {
                     batch.push(log);
                     if batch.len() >= batch_size {
-                        flush_batch(&mut batch, &agent_batch, &sinks_batch, &rate_limiter_batch, &source_batch).await;
+                        flush_batch(&mut batch, &agent_batch, &dispatcher_batch, &source_batch, &filter_batch, &active_response_batch, &control_state_batch).await;
                         last_flush = tokio::time::Instant::now();
                     }
                 }
                 _ = sleep => {
                     if !batch.is_empty() {
-                        flush_batch(&mut batch, &agent_batch, &sinks_batch, &rate_limiter_batch, &source_batch).await;
+                        flush_batch(&mut batch, &agent_batch, &dispatcher_batch, &source_batch, &filter_batch, &active_response_batch, &control_state_batch).await;
                     }
                     last_flush = tokio::time::Instant::now();
                 }
@@ -209,71 +345,146 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     aggregator.run(rx, move |combined_log| {
         let filter = Arc::clone(&filter);
         let analysis_tx = analysis_tx.clone();
-        
+        let dispatcher = Arc::clone(&dispatcher);
+        let source = source.clone();
+        let active_response = active_response.clone();
+        let control_state = Arc::clone(&control_state);
+
         async move {
-            process_log(combined_log, filter, analysis_tx).await;
+            process_log(combined_log, filter, analysis_tx, dispatcher, source, active_response, control_state).await;
         }
     }).await;
 
     Ok(())
 }
 
+fn build_response_action(settings: &Settings) -> Box<dyn ResponseAction> {
+    if settings.response.action.eq_ignore_ascii_case("ipset") {
+        Box::new(IpsetAction {
+            set_name: settings.response.ipset_name.clone(),
+        })
+    } else {
+        Box::new(ShellCommandAction {
+            ban_template: settings.response.shell_ban_command.clone(),
+            unban_template: settings.response.shell_unban_command.clone(),
+        })
+    }
+}
+
 async fn process_log(
     line: String,
     filter: Arc<LogFilter>,
     analysis_tx: mpsc::Sender<String>,
+    dispatcher: Arc<Dispatcher>,
+    source: LogSource,
+    active_response: Option<Arc<ActiveResponse>>,
+    control_state: Arc<ControlState>,
 ) {
-    if filter.is_suspicious(&line) {
-        log_sentinel::metrics::SUSPICIOUS_LOGS.inc();
-        info!(line = %line, "Suspicious log detected, queuing for batch analysis");
-        let _ = analysis_tx.send(line).await;
-    } else {
-        debug!("Log line not suspicious, skipping");
+    if control_state.is_paused() {
+        debug!("Pipeline paused via control socket, dropping log line");
+        return;
+    }
+
+    match filter.classify(&line) {
+        FilterVerdict::Drop => debug!("Log line not suspicious, skipping"),
+        FilterVerdict::Escalate => {
+            log_sentinel::metrics::SUSPICIOUS_LOGS.inc();
+            info!(line = %line, "Suspicious log detected, queuing for batch analysis");
+            let _ = analysis_tx.send(line).await;
+        }
+        FilterVerdict::Alert(score) => {
+            log_sentinel::metrics::SUSPICIOUS_LOGS.inc();
+            // No LLM backend is involved in a Bayes-only alert, so it's
+            // labeled distinctly from the provider/model dimensions.
+            log_sentinel::metrics::CONFIRMED_THREATS
+                .with_label_values(&["bayes", "local", "HIGH"])
+                .inc();
+            info!(line = %line, score, "Bayesian pre-filter confident enough to alert without an LLM call");
+            let alert = SecurityAlert {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                source_type: source.as_str().to_string(),
+                severity: "HIGH".to_string(),
+                attack_type: "Unknown (Bayes)".to_string(),
+                description: format!("Local Bayesian classifier score {:.3} exceeded the high threshold", score),
+                original_log: line,
+            };
+            if let Err(e) = dispatcher.dispatch(&alert).await {
+                error!(error = %e, "Error dispatching bayes pre-filter alert");
+            }
+            if let Some(response) = &active_response {
+                response.record_alert(&alert).await;
+            }
+            control_state.record_alert(&alert).await;
+        }
     }
 }
 
 async fn flush_batch(
     batch: &mut Vec<String>,
     agent: &Arc<Agent>,
-    sinks: &Arc<Vec<Box<dyn AlertSink>>>,
-    rate_limiter: &Arc<AlertRateLimiter>,
+    dispatcher: &Arc<Dispatcher>,
     source: &LogSource,
+    filter: &Arc<LogFilter>,
+    active_response: &Option<Arc<ActiveResponse>>,
+    control_state: &Arc<ControlState>,
 ) {
     let lines_to_analyze = std::mem::take(batch);
     let count = lines_to_analyze.len();
     info!(count, "Flushing analysis batch");
-    log_sentinel::metrics::ANALYSIS_BATCHES.inc();
+    let (provider_label, model_label) = agent.metric_labels();
+    log_sentinel::metrics::ANALYSIS_BATCHES
+        .with_label_values(&[&provider_label, model_label])
+        .inc();
 
     let start = std::time::Instant::now();
-    let alerts = agent.analyze_batch(&lines_to_analyze, source).await;
-    let duration = start.elapsed().as_secs_f64();
-    log_sentinel::metrics::ANALYSIS_LATENCY.observe(duration);
-
-    if !alerts.is_empty() {
-        let dispatcher = Dispatcher::new(Arc::clone(sinks), Arc::clone(rate_limiter));
-        for alert in alerts {
-            log_sentinel::metrics::CONFIRMED_THREATS.inc();
-            info!(
-                severity = %alert.severity,
-                attack_type = %alert.attack_type,
-                "[CONFIRMED THREAT FROM BATCH]"
-            );
-            if let Err(e) = dispatcher.dispatch(&alert).await {
-                error!(error = %e, "Error dispatching alert from batch");
-            }
+    // Stream verdicts in as they're generated so a confirmed threat near the
+    // front of a large batch is dispatched immediately instead of waiting on
+    // the rest of the window.
+    let stream = agent.analyze_batch_stream(&lines_to_analyze, source).await;
+    tokio::pin!(stream);
+
+    let mut confirmed_lines: std::collections::HashSet<String> = std::collections::HashSet::new();
+    while let Some(alert) = stream.next().await {
+        confirmed_lines.insert(alert.original_log.clone());
+        log_sentinel::metrics::CONFIRMED_THREATS
+            .with_label_values(&[&provider_label, model_label, &alert.severity])
+            .inc();
+        info!(
+            severity = %alert.severity,
+            attack_type = %alert.attack_type,
+            "[CONFIRMED THREAT FROM BATCH]"
+        );
+        if let Err(e) = dispatcher.dispatch(&alert).await {
+            error!(error = %e, "Error dispatching alert from batch");
         }
-    } else {
+        if let Some(response) = active_response {
+            response.record_alert(&alert).await;
+        }
+        control_state.record_alert(&alert).await;
+    }
+    log_sentinel::metrics::ANALYSIS_LATENCY
+        .with_label_values(&[&provider_label, model_label])
+        .observe(start.elapsed().as_secs_f64());
+
+    // Feed the LLM's verdicts back to the local Bayesian pre-filter so it
+    // keeps learning which tokens actually correlate with confirmed threats.
+    for line in &lines_to_analyze {
+        filter.train(line, confirmed_lines.contains(line)).await;
+    }
+
+    if confirmed_lines.is_empty() {
         info!("Batch analysis complete: no threats confirmed");
     }
 }
 
-fn create_sinks(settings: &Settings) -> Vec<Box<dyn AlertSink>> {
+fn create_sinks(settings: &Settings, http_client: Arc<reqwest::Client>) -> Vec<Box<dyn AlertSink>> {
     let mut sinks: Vec<Box<dyn AlertSink>> = Vec::new();
 
     if settings.bff.enabled {
         sinks.push(Box::new(BffSink::new(
             settings.bff.url.clone(),
             settings.bff.token.clone(),
+            Arc::clone(&http_client),
         )));
     }
 
@@ -284,7 +495,48 @@ fn create_sinks(settings: &Settings) -> Vec<Box<dyn AlertSink>> {
     }
 
     if settings.email.enabled {
-        sinks.push(Box::new(EmailSink::new(settings.email.recipient.clone(), settings.email.from.clone(), settings.email.api_url.clone())   ));
+        sinks.push(Box::new(EmailSink::new(
+            settings.email.recipient.clone(),
+            settings.email.from.clone(),
+            settings.email.api_url.clone(),
+            Arc::clone(&http_client),
+        )));
+    }
+
+    if settings.redis.enabled {
+        match RedisSink::new(
+            &settings.redis.url,
+            settings.redis.stream_key.clone(),
+            settings.redis.max_len,
+            settings.redis.use_pubsub,
+            settings.redis.channel.clone(),
+        ) {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(e) => error!(error = %e, "Failed to initialize RedisSink, skipping"),
+        }
+    }
+
+    if settings.exec.enabled {
+        sinks.push(Box::new(ExecSink::new(
+            settings.exec.command.clone(),
+            settings.exec.args.clone(),
+            settings.exec.timeout_secs,
+        )));
+    }
+
+    if settings.firewall.enabled {
+        match FirewallSink::new(&settings.firewall) {
+            Ok(sink) => {
+                let sink = Arc::new(sink);
+                let expiry_sink = Arc::clone(&sink);
+                let sweep_secs = settings.firewall.expiry_sweep_secs;
+                tokio::spawn(async move {
+                    expiry_sink.run_expiry_loop(sweep_secs).await;
+                });
+                sinks.push(Box::new(sink));
+            }
+            Err(e) => error!(error = %e, "Failed to initialize FirewallSink, skipping"),
+        }
     }
 
     sinks
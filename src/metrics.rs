@@ -1,4 +1,9 @@
-use prometheus::{Registry, Counter, Histogram, HistogramOpts, opts, register_counter_with_registry, register_histogram_with_registry};
+use prometheus::{
+    Registry, Counter, CounterVec, Histogram, HistogramOpts, HistogramVec, Opts, opts,
+    register_counter_with_registry, register_counter_vec_with_registry,
+    register_histogram_with_registry, register_histogram_vec_with_registry,
+};
+use prometheus::core::Collector;
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -14,24 +19,61 @@ lazy_static! {
         REGISTRY
     ).unwrap();
 
-    pub static ref ANALYSIS_BATCHES: Counter = register_counter_with_registry!(
-        opts!("log_sentinel_analysis_batches_total", "Total number of batches sent to AI for analysis"),
+    pub static ref ANALYSIS_BATCHES: CounterVec = register_counter_vec_with_registry!(
+        Opts::new("log_sentinel_analysis_batches_total", "Total number of batches sent to AI for analysis"),
+        &["provider", "model"],
         REGISTRY
     ).unwrap();
 
-    pub static ref CONFIRMED_THREATS: Counter = register_counter_with_registry!(
-        opts!("log_sentinel_confirmed_threats_total", "Total number of security threats confirmed by AI"),
+    /// Labeled by `severity` as well as `provider`/`model` so dashboards can
+    /// alert on a CRITICAL spike without first summing across backends.
+    pub static ref CONFIRMED_THREATS: CounterVec = register_counter_vec_with_registry!(
+        Opts::new("log_sentinel_confirmed_threats_total", "Total number of security threats confirmed by AI"),
+        &["provider", "model", "severity"],
         REGISTRY
     ).unwrap();
 
-    pub static ref DISPATCH_FAILURES: Counter = register_counter_with_registry!(
-        opts!("log_sentinel_dispatch_failures_total", "Total number of errors when sending alerts to sinks"),
+    pub static ref DISPATCH_FAILURES: CounterVec = register_counter_vec_with_registry!(
+        Opts::new("log_sentinel_dispatch_failures_total", "Total number of errors when sending alerts to sinks"),
+        &["provider", "model"],
         REGISTRY
     ).unwrap();
 
-    pub static ref ANALYSIS_LATENCY: Histogram = register_histogram_with_registry!(
+    pub static ref ANALYSIS_LATENCY: HistogramVec = register_histogram_vec_with_registry!(
         HistogramOpts::new("log_sentinel_analysis_latency_seconds", "AI analysis latency in seconds")
             .buckets(vec![0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0]),
+        &["provider", "model"],
         REGISTRY
     ).unwrap();
+
+    /// Counts responses that still failed JSON Schema validation after the
+    /// one automatic re-prompt, so an operator can tell a chatty-but-wrong
+    /// model apart from one that's simply quiet.
+    pub static ref SCHEMA_VALIDATION_FAILURES: Counter = register_counter_with_registry!(
+        opts!("log_sentinel_schema_validation_failures_total", "Total number of LLM responses rejected by JSON Schema validation after a re-prompt"),
+        REGISTRY
+    ).unwrap();
+
+    pub static ref TOKENS_PROMPT: CounterVec = register_counter_vec_with_registry!(
+        Opts::new("log_sentinel_tokens_prompt_total", "Total number of prompt tokens billed by the LLM provider"),
+        &["provider", "model"],
+        REGISTRY
+    ).unwrap();
+
+    pub static ref TOKENS_COMPLETION: CounterVec = register_counter_vec_with_registry!(
+        Opts::new("log_sentinel_tokens_completion_total", "Total number of completion tokens billed by the LLM provider"),
+        &["provider", "model"],
+        REGISTRY
+    ).unwrap();
+}
+
+/// Sums every label combination of a `CounterVec` into a single total, for
+/// callers (like the control socket's `stats` command) that only want an
+/// aggregate and don't care about the provider/model breakdown.
+pub fn sum_counter_vec(vec: &CounterVec) -> f64 {
+    vec.collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .map(|metric| metric.get_counter().get_value())
+        .sum()
 }
@@ -50,7 +50,7 @@ pub struct AnalysisResult {
   pub source: LogSourceDetail,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SecurityAlert {
     pub timestamp: String,
     pub source_type: String,
@@ -0,0 +1,392 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+use crate::config::ResponseConfig;
+use crate::models::SecurityAlert;
+
+/// A pluggable reaction to a confirmed repeat offender.
+#[async_trait]
+pub trait ResponseAction: Send + Sync {
+    async fn ban(&self, ip: IpAddr, duration: Duration) -> Result<(), Box<dyn std::error::Error>>;
+    async fn unban(&self, ip: IpAddr) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Adds/removes the attacker's IP from an `ipset` set by shelling out to the
+/// `ipset` CLI. nftables sets are not handled here; use `ShellCommandAction`.
+pub struct IpsetAction {
+    pub set_name: String,
+}
+
+#[async_trait]
+impl ResponseAction for IpsetAction {
+    async fn ban(&self, ip: IpAddr, _duration: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        let status = Command::new("ipset")
+            .args(["add", &self.set_name, &ip.to_string(), "-exist"])
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(format!("ipset add exited with {}", status).into());
+        }
+        Ok(())
+    }
+
+    async fn unban(&self, ip: IpAddr) -> Result<(), Box<dyn std::error::Error>> {
+        let status = Command::new("ipset")
+            .args(["del", &self.set_name, &ip.to_string()])
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(format!("ipset del exited with {}", status).into());
+        }
+        Ok(())
+    }
+}
+
+/// Runs a configurable shell command template, substituting `{ip}` and
+/// `{duration_secs}`, for both ban and unban (e.g. custom nftables scripts).
+pub struct ShellCommandAction {
+    pub ban_template: String,
+    pub unban_template: String,
+}
+
+fn render(template: &str, ip: IpAddr, duration: Duration) -> String {
+    template
+        .replace("{ip}", &ip.to_string())
+        .replace("{duration_secs}", &duration.as_secs().to_string())
+}
+
+async fn run_shell(command: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("sh").arg("-c").arg(command).status().await?;
+    if !status.success() {
+        return Err(format!("command exited with {}: {}", status, command).into());
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl ResponseAction for ShellCommandAction {
+    async fn ban(&self, ip: IpAddr, duration: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        run_shell(&render(&self.ban_template, ip, duration)).await
+    }
+
+    async fn unban(&self, ip: IpAddr) -> Result<(), Box<dyn std::error::Error>> {
+        run_shell(&render(&self.unban_template, ip, Duration::from_secs(0))).await
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ActiveBan {
+    ip: String,
+    expires_at_ms: i64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+pub(crate) fn extract_ip(regex: &Regex, line: &str) -> Option<IpAddr> {
+    let captures = regex.captures(line)?;
+    // Prefer the last capture group, so per-source override regexes written
+    // with one (e.g. `client=(\d+\.\d+\.\d+\.\d+)`) extract just the address
+    // rather than the whole match. Regexes with no groups fall back to group 0.
+    let matched = captures.iter().flatten().last()?.as_str();
+    IpAddr::from_str(matched).ok()
+}
+
+/// Used whenever a `LogSource` has no entry in `ResponseConfig::ip_regex`.
+const DEFAULT_IP_PATTERN: &str = r"(?:\d{1,3}\.){3}\d{1,3}|[0-9a-fA-F:]{3,}:[0-9a-fA-F:]+";
+
+/// Turns repeated HIGH/CRITICAL detections from the same source IP into a
+/// ban: a fail2ban-style reactive layer on top of the LLM-driven detection.
+pub struct ActiveResponse {
+    default_ip_regex: Regex,
+    ip_regex_by_source: HashMap<String, Regex>,
+    window: Duration,
+    threshold: usize,
+    ban_duration: Duration,
+    action: Box<dyn ResponseAction>,
+    offenses: DashMap<IpAddr, VecDeque<Instant>>,
+    bans: DashMap<IpAddr, Instant>,
+    state_path: PathBuf,
+}
+
+impl ActiveResponse {
+    pub fn new(config: &ResponseConfig, action: Box<dyn ResponseAction>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut ip_regex_by_source = HashMap::new();
+        for (source, pattern) in &config.ip_regex {
+            ip_regex_by_source.insert(source.to_lowercase(), Regex::new(pattern)?);
+        }
+
+        Ok(Self {
+            default_ip_regex: Regex::new(DEFAULT_IP_PATTERN)?,
+            ip_regex_by_source,
+            window: Duration::from_secs(config.window_secs),
+            threshold: config.threshold,
+            ban_duration: Duration::from_secs(config.ban_duration_secs),
+            action,
+            offenses: DashMap::new(),
+            bans: DashMap::new(),
+            state_path: PathBuf::from(&config.state_path),
+        })
+    }
+
+    /// The regex for `source_type` (matched case-insensitively against the
+    /// configured overrides), falling back to the default IPv4/IPv6 pattern.
+    fn ip_regex_for(&self, source_type: &str) -> &Regex {
+        self.ip_regex_by_source
+            .get(&source_type.to_lowercase())
+            .unwrap_or(&self.default_ip_regex)
+    }
+
+    /// Re-apply (or discard expired) bans recorded before a restart.
+    pub async fn restore(&self) {
+        let Ok(raw) = std::fs::read_to_string(&self.state_path) else {
+            return;
+        };
+        let Ok(saved) = serde_json::from_str::<Vec<ActiveBan>>(&raw) else {
+            warn!(path = %self.state_path.display(), "Active-response state file unreadable, starting clean");
+            return;
+        };
+
+        let now = now_ms();
+        for ban in saved {
+            let Ok(ip) = IpAddr::from_str(&ban.ip) else { continue };
+            if ban.expires_at_ms <= now {
+                continue;
+            }
+            let remaining = Duration::from_millis((ban.expires_at_ms - now) as u64);
+            if let Err(e) = self.action.ban(ip, remaining).await {
+                error!(error = %e, ip = %ip, "Failed to re-apply ban on restart");
+                continue;
+            }
+            self.bans.insert(ip, Instant::now() + remaining);
+            info!(ip = %ip, remaining_secs = remaining.as_secs(), "Re-applied ban from previous run");
+        }
+    }
+
+    fn persist(&self) {
+        let now = Instant::now();
+        let now_wall = now_ms();
+        let entries: Vec<ActiveBan> = self
+            .bans
+            .iter()
+            .map(|entry| {
+                let remaining = entry.value().saturating_duration_since(now);
+                ActiveBan {
+                    ip: entry.key().to_string(),
+                    expires_at_ms: now_wall + remaining.as_millis() as i64,
+                }
+            })
+            .collect();
+
+        if let Ok(bytes) = serde_json::to_vec_pretty(&entries) {
+            let tmp = self.state_path.with_extension("tmp");
+            if std::fs::write(&tmp, bytes).and_then(|_| std::fs::rename(&tmp, &self.state_path)).is_err() {
+                warn!(path = %self.state_path.display(), "Failed to persist active-response ban state");
+            }
+        }
+    }
+
+    /// Record a confirmed HIGH/CRITICAL alert; bans the source IP once it
+    /// crosses `threshold` offenses inside the sliding window.
+    pub async fn record_alert(&self, alert: &SecurityAlert) {
+        if !matches!(alert.severity.as_str(), "HIGH" | "CRITICAL") {
+            return;
+        }
+
+        let Some(ip) = extract_ip(self.ip_regex_for(&alert.source_type), &alert.original_log) else {
+            return;
+        };
+
+        if self.bans.contains_key(&ip) {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entry = self.offenses.entry(ip).or_insert_with(VecDeque::new);
+        entry.push_back(now);
+        while entry.front().map(|t| now.duration_since(*t) > self.window).unwrap_or(false) {
+            entry.pop_front();
+        }
+
+        if entry.len() >= self.threshold {
+            entry.clear();
+            drop(entry);
+            self.ban(ip).await;
+        }
+    }
+
+    async fn ban(&self, ip: IpAddr) {
+        match self.action.ban(ip, self.ban_duration).await {
+            Ok(()) => {
+                warn!(ip = %ip, duration_secs = self.ban_duration.as_secs(), "Active response: banned repeat offender");
+                self.bans.insert(ip, Instant::now() + self.ban_duration);
+                self.persist();
+            }
+            Err(e) => error!(error = %e, ip = %ip, "Failed to ban IP"),
+        }
+    }
+
+    /// Background task: periodically sweep for expired bans and unban them.
+    pub async fn run_expiry_loop(self: Arc<Self>, interval_secs: u64) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let expired: Vec<IpAddr> = self
+                .bans
+                .iter()
+                .filter(|e| *e.value() <= now)
+                .map(|e| *e.key())
+                .collect();
+
+            for ip in expired {
+                if let Err(e) = self.action.unban(ip).await {
+                    error!(error = %e, ip = %ip, "Failed to unban expired IP");
+                    continue;
+                }
+                self.bans.remove(&ip);
+                info!(ip = %ip, "Active response: ban expired, unbanned");
+            }
+            self.persist();
+        }
+    }
+
+    pub fn active_bans(&self) -> HashMap<IpAddr, Duration> {
+        let now = Instant::now();
+        self.bans
+            .iter()
+            .map(|e| (*e.key(), e.value().saturating_duration_since(now)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingAction {
+        bans: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ResponseAction for CountingAction {
+        async fn ban(&self, _ip: IpAddr, _duration: Duration) -> Result<(), Box<dyn std::error::Error>> {
+            self.bans.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+        async fn unban(&self, _ip: IpAddr) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    fn config() -> ResponseConfig {
+        ResponseConfig {
+            enabled: true,
+            threshold: 3,
+            window_secs: 60,
+            ban_duration_secs: 300,
+            ip_regex: HashMap::new(),
+            action: "shell".to_string(),
+            ipset_name: "banned".to_string(),
+            shell_ban_command: "true".to_string(),
+            shell_unban_command: "true".to_string(),
+            state_path: std::env::temp_dir()
+                .join(format!("active-response-test-{}.json", std::process::id()))
+                .to_string_lossy()
+                .to_string(),
+            expiry_sweep_secs: 30,
+        }
+    }
+
+    fn alert(ip: &str, severity: &str) -> SecurityAlert {
+        SecurityAlert {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            source_type: "Nginx".to_string(),
+            severity: severity.to_string(),
+            attack_type: "Brute Force".to_string(),
+            description: "repeated auth failures".to_string(),
+            original_log: format!("Failed login from {}", ip),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bans_after_threshold_offenses() {
+        let bans = Arc::new(AtomicUsize::new(0));
+        let response = ActiveResponse::new(&config(), Box::new(CountingAction { bans: Arc::clone(&bans) })).unwrap();
+
+        response.record_alert(&alert("10.0.0.5", "HIGH")).await;
+        response.record_alert(&alert("10.0.0.5", "HIGH")).await;
+        assert_eq!(bans.load(Ordering::SeqCst), 0);
+
+        response.record_alert(&alert("10.0.0.5", "HIGH")).await;
+        assert_eq!(bans.load(Ordering::SeqCst), 1);
+
+        let _ = std::fs::remove_file(&response.state_path);
+    }
+
+    #[tokio::test]
+    async fn test_low_severity_ignored() {
+        let bans = Arc::new(AtomicUsize::new(0));
+        let response = ActiveResponse::new(&config(), Box::new(CountingAction { bans: Arc::clone(&bans) })).unwrap();
+
+        for _ in 0..5 {
+            response.record_alert(&alert("10.0.0.6", "LOW")).await;
+        }
+        assert_eq!(bans.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_per_source_ip_regex_override() {
+        let mut cfg = config();
+        cfg.threshold = 1;
+        // Only digits after "client=" count as the IP for Nginx; every other
+        // source keeps falling back to the default IPv4/IPv6 pattern.
+        cfg.ip_regex.insert("nginx".to_string(), r"client=(\d+\.\d+\.\d+\.\d+)".to_string());
+
+        let bans = Arc::new(AtomicUsize::new(0));
+        let response = ActiveResponse::new(&cfg, Box::new(CountingAction { bans: Arc::clone(&bans) })).unwrap();
+
+        let mut nginx_alert = alert("10.0.0.7", "HIGH");
+        nginx_alert.source_type = "Nginx".to_string();
+        nginx_alert.original_log = "no client= prefix here, just 10.0.0.7".to_string();
+        response.record_alert(&nginx_alert).await;
+        assert_eq!(bans.load(Ordering::SeqCst), 0, "Nginx override regex shouldn't match a bare IP");
+
+        let mut tomcat_alert = alert("10.0.0.7", "HIGH");
+        tomcat_alert.source_type = "Tomcat".to_string();
+        response.record_alert(&tomcat_alert).await;
+        assert_eq!(bans.load(Ordering::SeqCst), 1, "Tomcat should still use the default regex");
+
+        let _ = std::fs::remove_file(&response.state_path);
+    }
+
+    #[test]
+    fn test_extract_ip_prefers_capture_group_over_whole_match() {
+        let regex = Regex::new(r"client=(\d+\.\d+\.\d+\.\d+)").unwrap();
+        let ip = extract_ip(&regex, "request from client=10.0.0.7 rejected").unwrap();
+        assert_eq!(ip, "10.0.0.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_extract_ip_falls_back_to_whole_match_without_groups() {
+        let regex = Regex::new(DEFAULT_IP_PATTERN).unwrap();
+        let ip = extract_ip(&regex, "connection from 10.0.0.7 refused").unwrap();
+        assert_eq!(ip, "10.0.0.7".parse::<IpAddr>().unwrap());
+    }
+}
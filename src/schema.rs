@@ -0,0 +1,98 @@
+use jsonschema::JSONSchema;
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+
+lazy_static! {
+    /// Contract for a single-log verdict, e.g. the shape `Agent::analyze`
+    /// expects from `LLMProvider::analyze`.
+    static ref VERDICT_SCHEMA: JSONSchema = JSONSchema::compile(&json!({
+        "type": "object",
+        "properties": {
+            "severity": { "type": "string", "enum": ["LOW", "MEDIUM", "HIGH", "CRITICAL"] },
+            "attack_type": { "type": "string", "minLength": 1 },
+            "description": { "type": "string", "minLength": 1 }
+        },
+        "required": ["severity", "attack_type", "description"]
+    }))
+    .expect("verdict JSON schema must compile");
+
+    /// Contract for a batch response: a `results` array where each entry is
+    /// either a threat verdict (tagged with its `index`) or a benign
+    /// `status: "NULL"` placeholder.
+    static ref BATCH_SCHEMA: JSONSchema = JSONSchema::compile(&json!({
+        "type": "object",
+        "properties": {
+            "results": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "index": { "type": "integer", "minimum": 0 },
+                        "status": { "type": "string", "enum": ["NULL"] },
+                        "severity": { "type": "string", "enum": ["LOW", "MEDIUM", "HIGH", "CRITICAL"] },
+                        "attack_type": { "type": "string", "minLength": 1 },
+                        "description": { "type": "string", "minLength": 1 }
+                    },
+                    "required": ["index"],
+                    "if": {
+                        "properties": { "status": { "const": "NULL" } },
+                        "required": ["status"]
+                    },
+                    "then": {},
+                    "else": { "required": ["severity", "attack_type", "description"] }
+                }
+            }
+        },
+        "required": ["results"]
+    }))
+    .expect("batch JSON schema must compile");
+}
+
+/// Validates `instance` against `schema`, collapsing every violation into a
+/// single human-readable string suitable for appending to a re-prompt.
+fn validate(schema: &JSONSchema, instance: &Value) -> Result<(), String> {
+    schema
+        .validate(instance)
+        .map_err(|errors| errors.map(|e| e.to_string()).collect::<Vec<_>>().join("; "))
+}
+
+pub fn validate_verdict(instance: &Value) -> Result<(), String> {
+    validate(&VERDICT_SCHEMA, instance)
+}
+
+/// Accepts both the canonical `{"results": [...]}` envelope and a bare
+/// top-level array, since providers have historically returned either shape.
+pub fn validate_batch(instance: &Value) -> Result<(), String> {
+    let normalized = match instance {
+        Value::Array(_) => json!({ "results": instance }),
+        other => other.clone(),
+    };
+    validate(&BATCH_SCHEMA, &normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_batch_rejects_non_null_item_missing_verdict_fields() {
+        let instance = json!({ "results": [ { "index": 0, "severity": "HIGH" } ] });
+        assert!(validate_batch(&instance).is_err());
+    }
+
+    #[test]
+    fn test_validate_batch_accepts_null_item_without_verdict_fields() {
+        let instance = json!({ "results": [ { "index": 0, "status": "NULL" } ] });
+        assert!(validate_batch(&instance).is_ok());
+    }
+
+    #[test]
+    fn test_validate_batch_accepts_full_verdict_item() {
+        let instance = json!({
+            "results": [
+                { "index": 0, "severity": "HIGH", "attack_type": "SQLi", "description": "SQL injection" }
+            ]
+        });
+        assert!(validate_batch(&instance).is_ok());
+    }
+}
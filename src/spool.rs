@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::dispatcher::AlertSink;
+use crate::models::SecurityAlert;
+
+const DEAD_LETTER_DIR: &str = "dead-letter";
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Per-sink delivery bookkeeping for one spooled alert.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SinkDeliveryState {
+    pub acked: bool,
+    pub attempts: u32,
+    pub next_attempt_at_ms: i64,
+}
+
+/// A single alert waiting to be (re)delivered, serialized to its own file in
+/// the spool directory.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    pub id: String,
+    pub alert: SecurityAlert,
+    pub sink_states: HashMap<String, SinkDeliveryState>,
+    pub created_at_ms: i64,
+}
+
+impl SpoolEntry {
+    fn all_acked(&self) -> bool {
+        self.sink_states.values().all(|s| s.acked)
+    }
+}
+
+/// Disk-backed spool so alert delivery survives sink outages and process
+/// restarts: every alert is written to disk before any network call, a
+/// background worker retries with backoff until every sink acks, and
+/// permanently-stuck entries age out to a dead-letter file.
+pub struct AlertSpool {
+    dir: PathBuf,
+    /// Max time an entry may live in the spool before moving to dead-letter.
+    max_age_ms: i64,
+    /// Backpressure: reject new entries once the spool holds this many.
+    quota: usize,
+    /// Per-sink attempt ceiling; once every unacked sink hits this, the entry
+    /// moves to dead-letter regardless of its age.
+    max_attempts: u32,
+}
+
+/// Per-sink delivery status for one spool entry, as exposed to operators
+/// (e.g. over the control socket) without handing out the full alert payload.
+pub struct SpoolEntryStatus {
+    pub id: String,
+    pub attack_type: String,
+    pub severity: String,
+    pub created_at_ms: i64,
+    pub sinks: Vec<(String, SinkDeliveryState)>,
+}
+
+impl AlertSpool {
+    pub fn new(dir: impl Into<PathBuf>, max_age_secs: u64, quota: usize, max_attempts: u32) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        std::fs::create_dir_all(dir.join(DEAD_LETTER_DIR))?;
+        Ok(Self {
+            dir,
+            max_age_ms: (max_age_secs as i64) * 1000,
+            quota,
+            max_attempts,
+        })
+    }
+
+    /// Delivery status of every entry still pending in the spool, for
+    /// operator introspection.
+    pub fn status_snapshot(&self) -> Vec<SpoolEntryStatus> {
+        self.load_pending()
+            .into_iter()
+            .map(|entry| SpoolEntryStatus {
+                id: entry.id,
+                attack_type: entry.alert.attack_type,
+                severity: entry.alert.severity,
+                created_at_ms: entry.created_at_ms,
+                sinks: entry.sink_states.into_iter().collect(),
+            })
+            .collect()
+    }
+
+    fn entry_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn write_entry(&self, entry: &SpoolEntry) -> std::io::Result<()> {
+        let path = self.entry_path(&entry.id);
+        let tmp_path = path.with_extension("tmp");
+        let bytes = serde_json::to_vec_pretty(entry)?;
+        std::fs::write(&tmp_path, bytes)?;
+        std::fs::rename(&tmp_path, &path)
+    }
+
+    /// Count of not-yet-fully-acked entries currently on disk.
+    pub fn len(&self) -> usize {
+        std::fs::read_dir(&self.dir)
+            .map(|rd| {
+                rd.filter_map(|e| e.ok())
+                    .filter(|e| e.path().extension().map(|ext| ext == "json").unwrap_or(false))
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    pub fn is_over_quota(&self) -> bool {
+        self.len() >= self.quota
+    }
+
+    /// Write-ahead: persist the alert (with every configured sink pending)
+    /// before any network call is attempted. Returns the new entry.
+    pub fn enqueue(&self, alert: &SecurityAlert, sink_names: &[&str]) -> Result<SpoolEntry, String> {
+        if self.is_over_quota() {
+            return Err(format!("alert spool quota ({}) exceeded, applying backpressure", self.quota));
+        }
+
+        let now = now_millis();
+        let sink_states = sink_names
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    SinkDeliveryState {
+                        acked: false,
+                        attempts: 0,
+                        next_attempt_at_ms: now,
+                    },
+                )
+            })
+            .collect();
+
+        let entry = SpoolEntry {
+            id: Uuid::new_v4().to_string(),
+            alert: alert.clone(),
+            sink_states,
+            created_at_ms: now,
+        };
+
+        self.write_entry(&entry).map_err(|e| e.to_string())?;
+        Ok(entry)
+    }
+
+    /// Reload every pending entry from disk. Called on startup (and by the
+    /// background worker on each sweep) so alerts survive restarts.
+    pub fn load_pending(&self) -> Vec<SpoolEntry> {
+        let mut entries = Vec::new();
+        let Ok(rd) = std::fs::read_dir(&self.dir) else {
+            return entries;
+        };
+
+        for dir_entry in rd.filter_map(|e| e.ok()) {
+            let path = dir_entry.path();
+            if path.extension().map(|ext| ext != "json").unwrap_or(true) {
+                continue;
+            }
+            match std::fs::read_to_string(&path).and_then(|raw| {
+                serde_json::from_str::<SpoolEntry>(&raw).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            }) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!(path = %path.display(), error = %e, "Skipping unreadable spool entry"),
+            }
+        }
+
+        entries
+    }
+
+    fn remove_entry(&self, id: &str) {
+        let path = self.entry_path(id);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                error!(error = %e, path = %path.display(), "Failed to remove spool entry");
+            }
+        }
+    }
+
+    fn move_to_dead_letter(&self, entry: &SpoolEntry) {
+        let dead_path = self.dir.join(DEAD_LETTER_DIR).join(format!("{}.json", entry.id));
+        if let Ok(bytes) = serde_json::to_vec_pretty(entry) {
+            if let Err(e) = std::fs::write(&dead_path, bytes) {
+                error!(error = %e, "Failed to write dead-letter entry");
+            }
+        }
+        self.remove_entry(&entry.id);
+        warn!(id = %entry.id, "Alert moved to dead-letter after exceeding max age or max attempts with undelivered sinks");
+    }
+
+    /// Attempt delivery of one entry to every sink that isn't acked yet and
+    /// is due for its next attempt, updating and persisting state as it goes.
+    /// Returns `true` once the entry has left the spool, either fully
+    /// delivered or moved to dead-letter.
+    pub(crate) async fn attempt_delivery(&self, mut entry: SpoolEntry, sinks: &[Box<dyn AlertSink>]) -> bool {
+        let now = now_millis();
+
+        if now - entry.created_at_ms > self.max_age_ms && !entry.all_acked() {
+            self.move_to_dead_letter(&entry);
+            return true;
+        }
+
+        let exhausted = entry
+            .sink_states
+            .values()
+            .all(|s| s.acked || s.attempts >= self.max_attempts);
+        if exhausted && !entry.all_acked() {
+            self.move_to_dead_letter(&entry);
+            return true;
+        }
+
+        let mut changed = false;
+        for sink in sinks {
+            let Some(state) = entry.sink_states.get_mut(sink.name()) else {
+                continue;
+            };
+            if state.acked || state.next_attempt_at_ms > now {
+                continue;
+            }
+
+            match sink.send(&entry.alert).await {
+                Ok(()) => {
+                    state.acked = true;
+                    changed = true;
+                    debug!(id = %entry.id, sink = sink.name(), "Spooled alert delivered");
+                }
+                Err(e) => {
+                    state.attempts += 1;
+                    let backoff_secs = 2u64.saturating_pow(state.attempts.min(10)).min(3600);
+                    state.next_attempt_at_ms = now + (backoff_secs as i64) * 1000;
+                    changed = true;
+                    warn!(
+                        id = %entry.id,
+                        sink = sink.name(),
+                        error = %e,
+                        attempt = state.attempts,
+                        next_retry_secs = backoff_secs,
+                        "Spooled alert delivery failed, will retry with backoff"
+                    );
+                }
+            }
+        }
+
+        if entry.all_acked() {
+            self.remove_entry(&entry.id);
+            return true;
+        }
+
+        if changed {
+            if let Err(e) = self.write_entry(&entry) {
+                error!(error = %e, "Failed to persist spool entry after delivery attempt");
+            }
+        }
+
+        false
+    }
+
+    /// Background worker loop: periodically sweeps the spool directory and
+    /// retries undelivered alerts. Intended to be spawned once at startup.
+    pub async fn run_worker(self: Arc<Self>, sinks: Arc<Vec<Box<dyn AlertSink>>>, sweep_interval_secs: u64) {
+        info!(dir = %self.dir.display(), "Alert spool worker starting, resuming any pending entries");
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(sweep_interval_secs));
+        loop {
+            interval.tick().await;
+            for entry in self.load_pending() {
+                self.attempt_delivery(entry, &sinks).await;
+            }
+        }
+    }
+}
+
+/// RFC3339 timestamp of the current time (used by the spool's tests).
+pub fn now_rfc3339() -> String {
+    Utc::now().to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_alert() -> SecurityAlert {
+        SecurityAlert {
+            timestamp: now_rfc3339(),
+            source_type: "Nginx".to_string(),
+            severity: "HIGH".to_string(),
+            attack_type: "SQLi".to_string(),
+            description: "test".to_string(),
+            original_log: "' OR 1=1 --".to_string(),
+        }
+    }
+
+    fn test_spool() -> AlertSpool {
+        let dir = std::env::temp_dir().join(format!("spool-test-{}", Uuid::new_v4()));
+        AlertSpool::new(dir, 3600, 10, 5).unwrap()
+    }
+
+    #[test]
+    fn test_enqueue_and_load_pending() {
+        let spool = test_spool();
+        let entry = spool.enqueue(&sample_alert(), &["bff", "email"]).unwrap();
+        let pending = spool.load_pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, entry.id);
+        assert_eq!(pending[0].sink_states.len(), 2);
+    }
+
+    #[test]
+    fn test_quota_backpressure() {
+        let spool = AlertSpool::new(
+            std::env::temp_dir().join(format!("spool-quota-{}", Uuid::new_v4())),
+            3600,
+            1,
+            5,
+        )
+        .unwrap();
+        assert!(spool.enqueue(&sample_alert(), &["bff"]).is_ok());
+        assert!(spool.enqueue(&sample_alert(), &["bff"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_attempt_delivery_removes_fully_acked_entry() {
+        struct AlwaysOkSink;
+        #[async_trait::async_trait]
+        impl AlertSink for AlwaysOkSink {
+            async fn send(&self, _alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
+                Ok(())
+            }
+            fn name(&self) -> &'static str {
+                "bff"
+            }
+        }
+
+        let spool = test_spool();
+        let entry = spool.enqueue(&sample_alert(), &["bff"]).unwrap();
+        let sinks: Vec<Box<dyn AlertSink>> = vec![Box::new(AlwaysOkSink)];
+
+        let delivered = spool.attempt_delivery(entry, &sinks).await;
+        assert!(delivered);
+        assert_eq!(spool.load_pending().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_attempt_delivery_dead_letters_after_max_attempts() {
+        struct AlwaysFailSink;
+        #[async_trait::async_trait]
+        impl AlertSink for AlwaysFailSink {
+            async fn send(&self, _alert: &SecurityAlert) -> Result<(), Box<dyn std::error::Error>> {
+                Err("simulated outage".into())
+            }
+            fn name(&self) -> &'static str {
+                "bff"
+            }
+        }
+
+        let spool = AlertSpool::new(
+            std::env::temp_dir().join(format!("spool-attempts-{}", Uuid::new_v4())),
+            3600,
+            10,
+            2,
+        )
+        .unwrap();
+        let mut entry = spool.enqueue(&sample_alert(), &["bff"]).unwrap();
+        let sinks: Vec<Box<dyn AlertSink>> = vec![Box::new(AlwaysFailSink)];
+
+        for _ in 0..2 {
+            entry.sink_states.get_mut("bff").unwrap().next_attempt_at_ms = 0;
+            let delivered = spool.attempt_delivery(entry.clone(), &sinks).await;
+            assert!(!delivered);
+            entry = spool.load_pending().into_iter().find(|e| e.id == entry.id).unwrap();
+        }
+
+        entry.sink_states.get_mut("bff").unwrap().next_attempt_at_ms = 0;
+        let delivered = spool.attempt_delivery(entry, &sinks).await;
+        assert!(delivered);
+        assert_eq!(spool.load_pending().len(), 0);
+    }
+}
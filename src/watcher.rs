@@ -1,110 +1,227 @@
-use notify::{Watcher, RecursiveMode, Config};
-use std::path::Path;
+use notify::{Watcher, RecursiveMode, Config, EventKind};
+use std::path::{Path, PathBuf};
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::fs::File;
+use std::os::unix::fs::MetadataExt;
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
 
+/// Sidecar checkpoint recording how far the watcher has read into the log
+/// file, so a restart (or a rotation mid-tail) resumes instead of either
+/// re-reading from the top or silently skipping whatever was written while
+/// LogSentinel was down.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Checkpoint {
+    inode: u64,
+    offset: u64,
+}
+
+#[derive(Clone)]
 pub struct LogWatcher {
     path: String,
+    /// When set, the checkpoint lives here instead of next to the log file,
+    /// so it can be unified under a single `--data-dir` with the rest of
+    /// this instance's state (PID file, alert spool).
+    checkpoint_dir: Option<String>,
 }
 
 impl LogWatcher {
     pub fn new(path: &str) -> Self {
-        Self { path: path.to_string() }
+        Self {
+            path: path.to_string(),
+            checkpoint_dir: None,
+        }
+    }
+
+    pub fn with_checkpoint_dir(mut self, checkpoint_dir: impl Into<String>) -> Self {
+        self.checkpoint_dir = Some(checkpoint_dir.into());
+        self
+    }
+
+    fn checkpoint_path(&self) -> PathBuf {
+        match &self.checkpoint_dir {
+            Some(dir) => {
+                let sanitized = self.path.replace(['/', '\\'], "_");
+                PathBuf::from(dir).join(format!("{}.logsentinel-ckpt", sanitized))
+            }
+            None => PathBuf::from(format!("{}.logsentinel-ckpt", self.path)),
+        }
+    }
+
+    fn load_checkpoint(&self) -> Option<Checkpoint> {
+        let raw = std::fs::read_to_string(self.checkpoint_path()).ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    fn save_checkpoint(&self, checkpoint: Checkpoint) {
+        let path = self.checkpoint_path();
+        let tmp_path = path.with_extension("ckpt.tmp");
+        let bytes = match serde_json::to_vec(&checkpoint) {
+            Ok(b) => b,
+            Err(e) => {
+                error!(error = %e, "Failed to serialize watcher checkpoint");
+                return;
+            }
+        };
+        if let Err(e) = std::fs::write(&tmp_path, &bytes).and_then(|_| std::fs::rename(&tmp_path, &path)) {
+            error!(error = %e, path = %path.display(), "Failed to persist watcher checkpoint");
+        }
+    }
+
+    /// Open the log file, and resume from the checkpointed offset if it's
+    /// still the same file (matching inode) and the file hasn't shrunk out
+    /// from under it (truncation / `copytruncate` rotation). Otherwise start
+    /// from the top so nothing written before LogSentinel came up is lost,
+    /// falling back to tailing from the end only when there is no checkpoint
+    /// and the file is being watched for the very first time.
+    fn open_from_checkpoint(&self, first_open: bool) -> std::io::Result<(File, u64)> {
+        let file = File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        let inode = metadata.ino();
+        let size = metadata.len();
+
+        let offset = match self.load_checkpoint() {
+            Some(ckpt) if ckpt.inode == inode && ckpt.offset <= size => ckpt.offset,
+            Some(_) => 0, // rotated (different inode) or truncated (saved offset past EOF)
+            None if first_open => size, // no prior state: preserve the original tail-from-now behavior
+            None => 0,
+        };
+
+        let mut file = file;
+        file.seek(SeekFrom::Start(offset))?;
+        self.save_checkpoint(Checkpoint { inode, offset });
+        Ok((file, offset))
     }
 
     pub async fn watch(&self, tx: mpsc::Sender<String>) -> notify::Result<()> {
-        let path = self.path.clone();
-        
+        let this = self.clone();
+
         // Spawn a blocking task for the file watcher
         tokio::task::spawn_blocking(move || {
+            let path = this.path.clone();
             let (sync_tx, sync_rx) = std::sync::mpsc::channel();
-            
+
             let mut watcher = match notify::RecommendedWatcher::new(sync_tx, Config::default()) {
                 Ok(w) => w,
                 Err(e) => {
-                    eprintln!("Failed to create watcher: {:?}", e);
+                    error!(error = ?e, "Failed to create watcher");
                     return;
                 }
             };
-            
+
             if let Err(e) = watcher.watch(Path::new(&path), RecursiveMode::NonRecursive) {
-                eprintln!("Failed to start watching {}: {:?}", path, e);
+                error!(error = ?e, path = %path, "Failed to start watching file");
                 return;
             }
 
-            let file = match File::open(&path) {
-                Ok(f) => f,
-                Err(e) => {
-                    eprintln!("Could not open log file {}: {:?}", path, e);
-                    return;
-                }
-            };
-            
-            let mut reader = BufReader::new(file);
-            let mut pos = match reader.seek(SeekFrom::End(0)) {
-                Ok(p) => p,
+            let (mut reader, mut pos) = match this.open_from_checkpoint(true) {
+                Ok((file, pos)) => (BufReader::new(file), pos),
                 Err(e) => {
-                    eprintln!("Failed to seek to end: {:?}", e);
+                    error!(error = ?e, path = %path, "Could not open log file");
                     return;
                 }
             };
 
-            println!("Watching file changes...");
+            info!(path = %path, offset = pos, "Watching file changes...");
 
             for res in sync_rx {
                 match res {
                     Ok(event) => {
-                        // println!("Event received: {:?}", event); // Debug logging
-                        if event.kind.is_modify() {
-                            let mut file = match File::open(&path) {
-                                Ok(f) => f,
+                        if matches!(event.kind, EventKind::Remove(_) | EventKind::Modify(notify::event::ModifyKind::Name(_))) {
+                            warn!(path = %path, "Log file removed or renamed, reopening from offset 0 on next write");
+                            // Inotify watches bind to the inode at registration time, so
+                            // after a rename-and-recreate rotation the original watch
+                            // keeps pointing at the detached old inode and will never
+                            // fire again. Re-register against the path so the watch
+                            // picks up whatever inode now lives there.
+                            let _ = watcher.unwatch(Path::new(&path));
+                            if let Err(e) = watcher.watch(Path::new(&path), RecursiveMode::NonRecursive) {
+                                debug!(error = ?e, path = %path, "Could not re-watch path yet, will retry on next event");
+                            }
+                            match this.open_from_checkpoint(false) {
+                                Ok((file, new_pos)) => {
+                                    reader = BufReader::new(file);
+                                    pos = new_pos;
+                                }
                                 Err(e) => {
-                                    eprintln!("Error re-opening file: {:?}", e);
-                                    continue;
+                                    debug!(error = ?e, path = %path, "Rotated file not yet recreated, will retry on next event");
                                 }
-                            };
-                            
-                            if let Err(e) = file.seek(SeekFrom::Start(pos)) {
-                                eprintln!("Error seeking file: {:?}", e);
+                            }
+                            continue;
+                        }
+
+                        if !event.kind.is_modify() {
+                            continue;
+                        }
+
+                        let mut file = match File::open(&path) {
+                            Ok(f) => f,
+                            Err(e) => {
+                                error!(error = ?e, "Error re-opening file");
                                 continue;
                             }
-                            
-                            let mut reader = BufReader::new(file);
-                            let mut line = String::new();
-                            
-                            loop {
-                                match reader.read_line(&mut line) {
-                                    Ok(0) => break, // EOF
-                                    Ok(_) => {
-                                        // We need to block_on to send to async channel or use blocking send if available
-                                        // But tx is mpsc::Sender (async). 
-                                        // Better: use blocking_send if using mpsc::blocking (not std) or Handle::current().block_on
-                                        // actually tokio::sync::mpsc::Sender has blocking_send
-                                        if let Err(_) = tx.blocking_send(line.clone()) {
-                                            eprintln!("Receiver dropped");
-                                            return; 
-                                        }
-                                        line.clear();
-                                    }
-                                    Err(e) => {
-                                        eprintln!("Error reading line: {:?}", e);
-                                        break;
+                        };
+
+                        let inode = match file.metadata() {
+                            Ok(m) => m.ino(),
+                            Err(e) => {
+                                error!(error = ?e, "Failed to stat file");
+                                continue;
+                            }
+                        };
+
+                        // Rotation via rename-and-recreate, or truncation, can show up
+                        // as a plain modify event depending on the platform/filesystem.
+                        let checkpoint_inode = this.load_checkpoint().map(|c| c.inode);
+                        let file_len = file.metadata().map(|m| m.len()).unwrap_or(0);
+                        if checkpoint_inode.map(|i| i != inode).unwrap_or(false) || file_len < pos {
+                            info!(path = %path, "Detected log rotation or truncation, restarting from offset 0");
+                            pos = 0;
+                        }
+
+                        if let Err(e) = file.seek(SeekFrom::Start(pos)) {
+                            error!(error = ?e, "Error seeking file");
+                            continue;
+                        }
+
+                        reader = BufReader::new(file);
+                        let mut line = String::new();
+                        let mut sent_any = false;
+
+                        loop {
+                            match reader.read_line(&mut line) {
+                                Ok(0) => break, // EOF
+                                Ok(_) => {
+                                    if tx.blocking_send(line.clone()).is_err() {
+                                        warn!("Receiver dropped, stopping watcher");
+                                        return;
                                     }
+                                    sent_any = true;
+                                    line.clear();
+                                }
+                                Err(e) => {
+                                    error!(error = ?e, "Error reading line");
+                                    break;
                                 }
                             }
-                            // Update position
-                            match reader.stream_position() {
-                                Ok(new_pos) => pos = new_pos,
-                                Err(e) => eprintln!("Error getting stream position: {:?}", e),
+                        }
+
+                        match reader.stream_position() {
+                            Ok(new_pos) => {
+                                pos = new_pos;
+                                if sent_any {
+                                    this.save_checkpoint(Checkpoint { inode, offset: pos });
+                                }
                             }
+                            Err(e) => error!(error = ?e, "Error getting stream position"),
                         }
                     }
-                    Err(e) => println!("Error in watcher: {:?}", e),
+                    Err(e) => warn!(error = ?e, "Error in watcher"),
                 }
             }
         }).await.map_err(|e| notify::Error::generic(&e.to_string()))?;
-        
+
         Ok(())
     }
-}
\ No newline at end of file
+}